@@ -0,0 +1,208 @@
+use crate::trash::Retention;
+use serde_derive::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlConfig {
+    scanner: Option<TomlScanner>,
+    developer: Option<TomlDeveloper>,
+    trash: Option<TomlTrash>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlScanner {
+    exclude: Option<Vec<String>>,
+    stale_months: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlDeveloper {
+    project_roots: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TomlTrash {
+    max_age_days: Option<u32>,
+    max_size_gb: Option<f64>,
+}
+
+// How long files go unused before the stale view lists them, unless configured
+const DEFAULT_STALE_MONTHS: u32 = 12;
+
+/// User settings from ~/.config/diskord/config.toml
+#[derive(Clone)]
+pub struct DiskordConfig {
+    pub exclude: Vec<GlobPattern>,
+    pub stale_months: u32,
+    /// Where the Developer Tools tab looks for projects with build artifacts
+    pub project_roots: Vec<PathBuf>,
+    /// What emptying the trash purges, everything when no rule is set
+    pub trash_retention: Retention,
+}
+
+impl Default for DiskordConfig {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            stale_months: DEFAULT_STALE_MONTHS,
+            project_roots: dirs::home_dir().into_iter().collect(),
+            trash_retention: Retention::default(),
+        }
+    }
+}
+
+impl DiskordConfig {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|mut p| {
+            p.push("diskord");
+            p.push("config.toml");
+            p
+        })
+    }
+
+    pub fn load() -> Self {
+        let mut config = Self::default();
+
+        if let Some(p) = Self::path()
+            && let Ok(content) = fs::read_to_string(p)
+                && let Ok(toml_data) = toml::from_str::<TomlConfig>(&content) {
+                    let scanner = toml_data.scanner.unwrap_or_default();
+                    config.exclude = scanner
+                        .exclude
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|p| GlobPattern::new(p))
+                        .collect();
+                    if let Some(months) = scanner.stale_months {
+                        config.stale_months = months.max(1);
+                    }
+
+                    let developer = toml_data.developer.unwrap_or_default();
+                    if let Some(roots) = developer.project_roots {
+                        config.project_roots =
+                            roots.iter().map(|r| PathBuf::from(expand_tilde(r))).collect();
+                    }
+
+                    let trash = toml_data.trash.unwrap_or_default();
+                    config.trash_retention = Retention {
+                        max_age_days: trash.max_age_days,
+                        max_bytes: trash
+                            .max_size_gb
+                            .filter(|gb| *gb >= 0.0)
+                            .map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as u64),
+                    };
+                }
+
+        config
+    }
+}
+
+// Replaces a leading `~` with the home directory
+fn expand_tilde(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix('~')
+        && let Some(home) = dirs::home_dir() {
+            return format!("{}{}", home.to_string_lossy(), rest);
+        }
+    path.to_string()
+}
+
+/// A shell-style path pattern.
+///
+/// `*` and `?` never cross a `/`, `**` matches any number of directories.
+/// Patterns starting with `/` or `~` are matched against the full path, any
+/// other pattern may match at any depth (`.snapshots` is the same as `**/.snapshots`).
+#[derive(Clone, Debug)]
+pub struct GlobPattern {
+    parts: Vec<String>,
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Self {
+        let mut pattern = expand_tilde(pattern.trim().trim_end_matches('/'));
+        if !pattern.starts_with('/') {
+            pattern = format!("**/{}", pattern);
+        }
+
+        Self {
+            parts: pattern
+                .split('/')
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy();
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        match_parts(&self.parts, &components)
+    }
+}
+
+fn match_parts(parts: &[String], components: &[&str]) -> bool {
+    match parts.first() {
+        None => components.is_empty(),
+        Some(part) if part == "**" => {
+            (0..=components.len()).any(|skip| match_parts(&parts[1..], &components[skip..]))
+        }
+        Some(part) => match components.first() {
+            Some(component) => {
+                match_component(part.as_bytes(), component.as_bytes())
+                    && match_parts(&parts[1..], &components[1..])
+            }
+            None => false,
+        },
+    }
+}
+
+// Matches a single path component against `*` and `?` wildcards
+fn match_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some(b'*') => (0..=name.len()).any(|skip| match_component(&pattern[1..], &name[skip..])),
+        Some(b'?') => !name.is_empty() && match_component(&pattern[1..], &name[1..]),
+        Some(c) => name.first() == Some(c) && match_component(&pattern[1..], &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        GlobPattern::new(pattern).matches(Path::new(path))
+    }
+
+    #[test]
+    fn wildcards_stay_within_one_folder() {
+        assert!(matches("/srv/*", "/srv/www"));
+        assert!(!matches("/srv/*", "/srv/www/index.html"));
+        assert!(!matches("/srv/*", "/srv"));
+        assert!(matches("/var/log/*.gz", "/var/log/syslog.1.gz"));
+        assert!(!matches("/var/log/*.gz", "/var/log/old/syslog.gz"));
+        assert!(matches("/tmp/cache-??", "/tmp/cache-01"));
+        assert!(!matches("/tmp/cache-??", "/tmp/cache-1"));
+    }
+
+    #[test]
+    fn double_star_spans_folders() {
+        assert!(matches("/home/**/target", "/home/target"));
+        assert!(matches("/home/**/target", "/home/me/code/app/target"));
+        assert!(!matches("/home/**/target", "/home/me/target/debug"));
+        assert!(matches("/mnt/**", "/mnt/usb/photos"));
+    }
+
+    #[test]
+    fn only_leading_slashes_and_tildes_anchor() {
+        assert!(matches(".snapshots", "/.snapshots"));
+        assert!(matches(".snapshots/", "/home/.snapshots"));
+        assert!(!matches(".snapshots", "/home/.snapshots-old"));
+        assert!(matches("node_modules/*", "/code/app/node_modules/left-pad"));
+        assert!(!matches("/.snapshots", "/home/.snapshots"));
+
+        let home = dirs::home_dir().unwrap();
+        assert!(GlobPattern::new("~/Vaults/*").matches(&home.join("Vaults/work")));
+        assert!(!GlobPattern::new("~/Vaults/*").matches(Path::new("/backup/Vaults/work")));
+    }
+}
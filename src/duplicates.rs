@@ -0,0 +1,758 @@
+use crate::scanner::{DirEntry, SizeMode};
+use crate::trash;
+use std::collections::HashMap;
+use std::fs::{self, File, FileTimes, OpenOptions, Permissions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::Xxh3;
+
+// How often the worker reports hashing progress to the UI
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// Files that differ usually do so right at the start, so every candidate is
+// first compared on this many leading bytes before anything is read in full
+const PARTIAL_HASH_BYTES: u64 = 16 * 1024;
+
+const READ_BUFFER_BYTES: usize = 256 * 1024;
+
+/// Files with identical contents. All but one of them could go.
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    pub files: Vec<DirEntry>,
+}
+
+impl DuplicateGroup {
+    /// Space freed by keeping a single copy
+    pub fn reclaimable(&self, mode: SizeMode) -> u64 {
+        let sizes = self.files.iter().map(|f| f.size_in(mode));
+        let largest = sizes.clone().max().unwrap_or(0);
+        sizes.sum::<u64>() - largest
+    }
+}
+
+/// Biggest savings first
+pub fn rank_groups(groups: &mut [DuplicateGroup], mode: SizeMode) {
+    groups.sort_by_key(|g| std::cmp::Reverse(g.reclaimable(mode)));
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DuplicateProgress {
+    /// False while comparing leading bytes, true once whole files are hashed
+    pub full_pass: bool,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_read: u64,
+}
+
+pub enum DuplicateUpdate {
+    Progress(DuplicateProgress),
+    Finished(Vec<DuplicateGroup>),
+    Cancelled,
+}
+
+/// A duplicate search running on a worker thread. Poll `rx` for updates, drop or cancel to stop it.
+pub struct DuplicateJob {
+    pub path: PathBuf,
+    pub rx: Receiver<DuplicateUpdate>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DuplicateJob {
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for DuplicateJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Confirms which of the same-size `candidates` (see `ScanTree::same_size_files`)
+/// really hold the same bytes, by comparing content hashes.
+pub fn spawn_duplicate_search(path: &Path, candidates: Vec<Vec<DirEntry>>) -> DuplicateJob {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let worker_cancel = cancel.clone();
+    std::thread::spawn(move || {
+        find_duplicates(candidates, &worker_cancel, &tx);
+    });
+
+    DuplicateJob {
+        path: path.to_path_buf(),
+        rx,
+        cancel,
+    }
+}
+
+fn find_duplicates(candidates: Vec<Vec<DirEntry>>, cancel: &AtomicBool, tx: &Sender<DuplicateUpdate>) {
+    let mut reporter = Reporter {
+        progress: DuplicateProgress::default(),
+        last_update: Instant::now(),
+        tx,
+    };
+
+    // Small files are fully covered by the partial hash already
+    let Some(partial) = split_by_hash(candidates, Some(PARTIAL_HASH_BYTES), cancel, &mut reporter) else {
+        let _ = tx.send(DuplicateUpdate::Cancelled);
+        return;
+    };
+    let (mut confirmed, large): (Vec<_>, Vec<_>) = partial
+        .into_iter()
+        .partition(|group| group[0].size <= PARTIAL_HASH_BYTES);
+
+    reporter.progress = DuplicateProgress {
+        full_pass: true,
+        bytes_read: reporter.progress.bytes_read,
+        ..Default::default()
+    };
+    let Some(full) = split_by_hash(large, None, cancel, &mut reporter) else {
+        let _ = tx.send(DuplicateUpdate::Cancelled);
+        return;
+    };
+    confirmed.extend(full);
+
+    let mut groups: Vec<DuplicateGroup> = confirmed
+        .into_iter()
+        .map(|mut files| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            DuplicateGroup { files }
+        })
+        .collect();
+    rank_groups(&mut groups, SizeMode::default());
+    let _ = tx.send(DuplicateUpdate::Finished(groups));
+}
+
+struct Reporter<'a> {
+    progress: DuplicateProgress,
+    last_update: Instant,
+    tx: &'a Sender<DuplicateUpdate>,
+}
+
+impl Reporter<'_> {
+    fn read(&mut self, bytes: u64) {
+        self.progress.bytes_read += bytes;
+        if self.last_update.elapsed() >= PROGRESS_INTERVAL {
+            self.last_update = Instant::now();
+            let _ = self.tx.send(DuplicateUpdate::Progress(self.progress));
+        }
+    }
+}
+
+// Splits every group further by the hash of the first `limit` bytes (or the
+// whole file), dropping anything left without a twin. None when cancelled.
+fn split_by_hash(
+    groups: Vec<Vec<DirEntry>>,
+    limit: Option<u64>,
+    cancel: &AtomicBool,
+    reporter: &mut Reporter,
+) -> Option<Vec<Vec<DirEntry>>> {
+    reporter.progress.files_total = groups.iter().map(Vec::len).sum();
+
+    let mut result = Vec::new();
+    for group in groups {
+        let mut by_hash: HashMap<u128, Vec<DirEntry>> = HashMap::new();
+        for file in group {
+            // Unreadable files simply can't be shown to be duplicates
+            if let Some(hash) = hash_file(&file.path, limit, cancel, reporter) {
+                by_hash.entry(hash).or_default().push(file);
+            }
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            reporter.progress.files_done += 1;
+        }
+        result.extend(by_hash.into_values().filter(|files| files.len() > 1));
+    }
+    Some(result)
+}
+
+fn hash_file(path: &Path, limit: Option<u64>, cancel: &AtomicBool, reporter: &mut Reporter) -> Option<u128> {
+    let file = File::open(path).ok()?;
+    let mut reader = file.take(limit.unwrap_or(u64::MAX));
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; READ_BUFFER_BYTES];
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        };
+        hasher.update(&buffer[..read]);
+        reporter.read(read as u64);
+    }
+    Some(hasher.digest128())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Shares the data extents copy-on-write (Btrfs, XFS), the files stay independent
+    Reflink,
+    /// Another name for the kept file, writes to either path show up in both
+    HardLink,
+}
+
+impl LinkKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkKind::Reflink => "REFLINK",
+            LinkKind::HardLink => "HARDLINK",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "reflink" => Some(LinkKind::Reflink),
+            "hardlink" => Some(LinkKind::HardLink),
+            _ => None,
+        }
+    }
+}
+
+/// A duplicate that was replaced by a link to an identical copy, with what
+/// it takes to give it its own data back.
+#[derive(Clone, Debug)]
+pub struct DedupedFile {
+    pub path: PathBuf,
+    pub kept: PathBuf,
+    pub kind: LinkKind,
+    pub size: u64,
+    mode: u32,
+    modified: SystemTime,
+    accessed: SystemTime,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkProgress {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+pub enum LinkUpdate {
+    Progress(LinkProgress),
+    /// A duplicate was replaced by a link, or why it wasn't
+    Linked(PathBuf, Result<DedupedFile, String>),
+    /// A link got its own copy of the data back, or why it didn't
+    Unlinked(PathBuf, Result<(), String>),
+    Finished,
+    Cancelled,
+}
+
+/// Linking or unlinking on a worker thread, whole files are compared or copied
+/// on the way. Poll `rx` for updates, drop or cancel to stop it.
+pub struct LinkJob {
+    pub rx: Receiver<LinkUpdate>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl LinkJob {
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for LinkJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+struct LinkReporter<'a> {
+    progress: LinkProgress,
+    last_update: Instant,
+    tx: &'a Sender<LinkUpdate>,
+}
+
+impl LinkReporter<'_> {
+    fn advance(&mut self, bytes: u64) {
+        self.progress.bytes_done += bytes;
+        if self.last_update.elapsed() >= PROGRESS_INTERVAL {
+            self.last_update = Instant::now();
+            let _ = self.tx.send(LinkUpdate::Progress(self.progress));
+        }
+    }
+}
+
+/// Replaces each of `duplicates` with a link to `keep`, see `dedupe_file`
+pub fn spawn_dedupe(keep: PathBuf, duplicates: Vec<PathBuf>) -> LinkJob {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let worker_cancel = cancel.clone();
+    std::thread::spawn(move || {
+        // Both sides of every pair are read in full
+        let bytes_total = duplicates
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|meta| meta.len() * 2)
+            .sum();
+        let mut reporter = LinkReporter {
+            progress: LinkProgress {
+                bytes_done: 0,
+                bytes_total,
+            },
+            last_update: Instant::now(),
+            tx: &tx,
+        };
+        for duplicate in duplicates {
+            let result = dedupe_file(&keep, &duplicate, &worker_cancel, &mut |bytes| reporter.advance(bytes));
+            // Cancelling only stops a link that wasn't made yet
+            if result.is_err() && worker_cancel.load(Ordering::Relaxed) {
+                let _ = tx.send(LinkUpdate::Cancelled);
+                return;
+            }
+            if let Ok(record) = &result {
+                log_action("linked", record);
+            }
+            let _ = tx.send(LinkUpdate::Linked(duplicate, result));
+        }
+        let _ = tx.send(LinkUpdate::Finished);
+    });
+
+    LinkJob { rx, cancel }
+}
+
+/// Gives `file` its own copy of the data back, see `undo_dedupe`
+pub fn spawn_undo(file: DedupedFile) -> LinkJob {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let worker_cancel = cancel.clone();
+    std::thread::spawn(move || {
+        let mut reporter = LinkReporter {
+            progress: LinkProgress {
+                bytes_done: 0,
+                bytes_total: file.size,
+            },
+            last_update: Instant::now(),
+            tx: &tx,
+        };
+        let result = undo_dedupe(&file, &worker_cancel, &mut |bytes| reporter.advance(bytes));
+        if result.is_err() && worker_cancel.load(Ordering::Relaxed) {
+            let _ = tx.send(LinkUpdate::Cancelled);
+            return;
+        }
+        if result.is_ok() {
+            log_action("restored", &file);
+        }
+        let _ = tx.send(LinkUpdate::Unlinked(file.path, result));
+        let _ = tx.send(LinkUpdate::Finished);
+    });
+
+    LinkJob { rx, cancel }
+}
+
+/// Replaces `duplicate` with a reflink of `keep`, or a hard link when the
+/// filesystem can't share extents and both have the same owner and
+/// permissions. Both files are compared byte for byte right before, so nothing
+/// is lost if either changed since the duplicate search.
+pub fn dedupe_file(
+    keep: &Path,
+    duplicate: &Path,
+    cancel: &AtomicBool,
+    advance: &mut dyn FnMut(u64),
+) -> Result<DedupedFile, String> {
+    let keep_meta = fs::symlink_metadata(keep).map_err(|e| e.to_string())?;
+    let dup_meta = fs::symlink_metadata(duplicate).map_err(|e| e.to_string())?;
+    if !keep_meta.is_file() || !dup_meta.is_file() {
+        return Err("Only regular files can be deduplicated".to_string());
+    }
+    if keep_meta.dev() != dup_meta.dev() {
+        return Err("The copies are on different filesystems".to_string());
+    }
+    if keep_meta.ino() == dup_meta.ino() {
+        return Err("Already linked to the same file".to_string());
+    }
+    if keep_meta.len() != dup_meta.len() || !same_contents(keep, duplicate, cancel, advance).map_err(|e| e.to_string())? {
+        return Err("Contents changed since the duplicate search".to_string());
+    }
+
+    let record = DedupedFile {
+        path: duplicate.to_path_buf(),
+        kept: keep.to_path_buf(),
+        kind: LinkKind::Reflink,
+        size: dup_meta.len(),
+        mode: dup_meta.mode() & 0o7777,
+        modified: dup_meta.modified().map_err(|e| e.to_string())?,
+        accessed: dup_meta.accessed().map_err(|e| e.to_string())?,
+    };
+
+    // Build the replacement next to the duplicate, then swap it in with a single rename.
+    // A temp file that is already there isn't ours, so it's left alone.
+    let temp = temp_path(duplicate);
+    let target = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(record.mode)
+        .open(&temp)
+        .map_err(|e| e.to_string())?;
+    let kind = match reflink(keep, &target, &record) {
+        Ok(()) => LinkKind::Reflink,
+        // The filesystem can't share extents, any other error is a real failure
+        Err(e) if matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EXDEV | libc::EINVAL)) => {
+            drop(target);
+            fs::remove_file(&temp).map_err(|e| e.to_string())?;
+            // A hard link takes on the kept file's owner and permissions, a private
+            // copy must not become readable or writable by others through it
+            let owner = |meta: &fs::Metadata| (meta.uid(), meta.gid(), meta.mode());
+            if owner(&keep_meta) != owner(&dup_meta) {
+                return Err("No reflinks here, and a hard link would change its owner or permissions".to_string());
+            }
+            fs::hard_link(keep, &temp).map_err(|e| e.to_string())?;
+            LinkKind::HardLink
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            return Err(e.to_string());
+        }
+    };
+    if let Err(e) = fs::rename(&temp, duplicate) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.to_string());
+    }
+
+    let record = DedupedFile { kind, ..record };
+    Ok(record)
+}
+
+/// Gives a deduplicated path its own copy of the data again, with the
+/// permissions and timestamps it had before.
+pub fn undo_dedupe(file: &DedupedFile, cancel: &AtomicBool, advance: &mut dyn FnMut(u64)) -> Result<(), String> {
+    let temp = temp_path(&file.path);
+    let mut source = File::open(&file.path).map_err(|e| e.to_string())?;
+    let mut target = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .map_err(|e| e.to_string())?;
+    let result = (|| {
+        let mut buffer = vec![0; READ_BUFFER_BYTES];
+        loop {
+            check_cancel(cancel)?;
+            let read = read_full(&mut source, &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            target.write_all(&buffer[..read])?;
+            advance(read as u64);
+        }
+        restore_metadata(&target, file)?;
+        fs::rename(&temp, &file.path)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+/// Keeps a link for good, it's no longer offered for undoing in later sessions
+pub fn keep_link(file: &DedupedFile) {
+    log_action("kept", file);
+}
+
+// Clones `source` into the new file `target` and makes it look like the duplicate it replaces
+fn reflink(source: &Path, target: &File, like: &DedupedFile) -> io::Result<()> {
+    let source = File::open(source)?;
+    if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    restore_metadata(target, like)
+}
+
+fn restore_metadata(file: &File, like: &DedupedFile) -> io::Result<()> {
+    file.set_permissions(Permissions::from_mode(like.mode))?;
+    file.set_times(
+        FileTimes::new()
+            .set_modified(like.modified)
+            .set_accessed(like.accessed),
+    )
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.diskord-dedupe", name))
+}
+
+fn same_contents(a: &Path, b: &Path, cancel: &AtomicBool, advance: &mut dyn FnMut(u64)) -> io::Result<bool> {
+    let mut a = File::open(a)?;
+    let mut b = File::open(b)?;
+    let mut buffer_a = vec![0; READ_BUFFER_BYTES];
+    let mut buffer_b = vec![0; READ_BUFFER_BYTES];
+
+    loop {
+        check_cancel(cancel)?;
+        let read = read_full(&mut a, &mut buffer_a)?;
+        if read != read_full(&mut b, &mut buffer_b)? || buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+        advance(read as u64 * 2);
+    }
+}
+
+fn check_cancel(cancel: &AtomicBool) -> io::Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "Cancelled"));
+    }
+    Ok(())
+}
+
+// Fills as much of `buffer` as the file has left, so both sides stay in step
+fn read_full(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn log_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("diskord").join("dedupe.log"))
+}
+
+// Keeps a trail in ~/.local/share/diskord/dedupe.log of every path that was
+// swapped for a link, with everything undo_dedupe needs, so links can still be
+// undone after the session ended. Paths are percent-encoded like in the trash.
+fn log_action(action: &str, file: &DedupedFile) {
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent()
+        && fs::create_dir_all(parent).is_err()
+    {
+        return;
+    }
+
+    if let Ok(mut log) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(log, "{}", log_line(action, file));
+    }
+}
+
+fn log_line(action: &str, file: &DedupedFile) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{:o}\t{}\t{}\t{}\t{}",
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+        action,
+        file.kind.label().to_lowercase(),
+        file.size,
+        file.mode,
+        format_time(file.modified),
+        format_time(file.accessed),
+        trash::encode_path(&file.path),
+        trash::encode_path(&file.kept)
+    )
+}
+
+/// Links from earlier sessions that were neither undone nor kept, and that
+/// still are what they were made as.
+pub fn logged_links() -> Vec<DedupedFile> {
+    let Some(log) = log_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    let mut links = open_links(&log);
+    links.retain(still_linked);
+    links
+}
+
+// The last word on each path decides, a later undo or keep closes a link
+fn open_links(log: &str) -> Vec<DedupedFile> {
+    let mut links: Vec<DedupedFile> = Vec::new();
+    for (action, file) in log.lines().filter_map(parse_log_line) {
+        links.retain(|f| f.path != file.path);
+        if action == "linked" {
+            links.push(file);
+        }
+    }
+    links
+}
+
+fn parse_log_line(line: &str) -> Option<(&str, DedupedFile)> {
+    // Lines written before the log kept enough to undo are skipped
+    let [_, action, kind, size, mode, modified, accessed, path, kept] =
+        line.split('\t').collect::<Vec<_>>()[..]
+    else {
+        return None;
+    };
+    let file = DedupedFile {
+        path: trash::decode_path(path)?,
+        kept: trash::decode_path(kept)?,
+        kind: LinkKind::from_label(kind)?,
+        size: size.parse().ok()?,
+        mode: u32::from_str_radix(mode, 8).ok()?,
+        modified: parse_time(modified)?,
+        accessed: parse_time(accessed)?,
+    };
+    Some((action, file))
+}
+
+// A hard link has to still share the kept file's inode. A reflink is its own
+// file, it only counts while it still has the size and time it was given.
+fn still_linked(file: &DedupedFile) -> bool {
+    let (Ok(meta), Ok(kept)) = (fs::symlink_metadata(&file.path), fs::symlink_metadata(&file.kept)) else {
+        return false;
+    };
+    if !meta.is_file() || meta.len() != file.size {
+        return false;
+    }
+    match file.kind {
+        LinkKind::HardLink => meta.dev() == kept.dev() && meta.ino() == kept.ino(),
+        LinkKind::Reflink => meta.modified().ok() == Some(file.modified),
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:09}", since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+fn parse_time(text: &str) -> Option<SystemTime> {
+    let (secs, nanos) = text.split_once('.')?;
+    Some(UNIX_EPOCH + Duration::new(secs.parse().ok()?, nanos.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("diskord-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn link(keep: &Path, duplicate: &Path) -> Result<DedupedFile, String> {
+        dedupe_file(keep, duplicate, &AtomicBool::new(false), &mut |_| {})
+    }
+
+    #[test]
+    fn links_identical_copies() {
+        let dir = temp_dir("link");
+        fs::write(dir.join("keep"), "same").unwrap();
+        fs::write(dir.join("copy"), "same").unwrap();
+
+        let record = link(&dir.join("keep"), &dir.join("copy")).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("copy")).unwrap(), "same");
+        assert!(still_linked(&record));
+        if record.kind == LinkKind::HardLink {
+            let ino = |name| fs::metadata(dir.join(name)).unwrap().ino();
+            assert_eq!(ino("keep"), ino("copy"));
+        }
+        assert!(!temp_path(&dir.join("copy")).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_copies_that_changed() {
+        let dir = temp_dir("changed");
+        fs::write(dir.join("keep"), "same").unwrap();
+        fs::write(dir.join("copy"), "diff").unwrap();
+
+        assert!(link(&dir.join("keep"), &dir.join("copy")).is_err());
+        assert_eq!(fs::read_to_string(dir.join("copy")).unwrap(), "diff");
+        assert!(!temp_path(&dir.join("copy")).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_a_temp_file_it_did_not_create() {
+        let dir = temp_dir("busy");
+        fs::write(dir.join("keep"), "same").unwrap();
+        fs::write(dir.join("copy"), "same").unwrap();
+        fs::write(temp_path(&dir.join("copy")), "not ours").unwrap();
+
+        assert!(link(&dir.join("keep"), &dir.join("copy")).is_err());
+        assert_eq!(fs::read_to_string(temp_path(&dir.join("copy"))).unwrap(), "not ours");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn never_hard_links_a_private_copy_to_a_shared_one() {
+        let dir = temp_dir("private");
+        fs::write(dir.join("keep"), "same").unwrap();
+        fs::write(dir.join("copy"), "same").unwrap();
+        fs::set_permissions(dir.join("keep"), Permissions::from_mode(0o644)).unwrap();
+        fs::set_permissions(dir.join("copy"), Permissions::from_mode(0o600)).unwrap();
+
+        // A reflink keeps its own permissions, without reflinks nothing happens
+        match link(&dir.join("keep"), &dir.join("copy")) {
+            Ok(record) => assert_eq!(record.kind, LinkKind::Reflink),
+            Err(_) => assert!(!temp_path(&dir.join("copy")).exists()),
+        }
+        assert_eq!(fs::metadata(dir.join("copy")).unwrap().mode() & 0o777, 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn undo_gives_back_an_independent_copy() {
+        let dir = temp_dir("undo");
+        fs::write(dir.join("keep"), "same").unwrap();
+        fs::write(dir.join("copy"), "same").unwrap();
+        let before = fs::metadata(dir.join("copy")).unwrap();
+
+        let record = link(&dir.join("keep"), &dir.join("copy")).unwrap();
+        undo_dedupe(&record, &AtomicBool::new(false), &mut |_| {}).unwrap();
+        let after = fs::metadata(dir.join("copy")).unwrap();
+        assert_ne!(after.ino(), fs::metadata(dir.join("keep")).unwrap().ino());
+        assert_eq!(after.mode(), before.mode());
+        assert_eq!(after.modified().unwrap(), before.modified().unwrap());
+
+        fs::write(dir.join("keep"), "changed").unwrap();
+        assert_eq!(fs::read_to_string(dir.join("copy")).unwrap(), "same");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replays_the_log() {
+        let file = |path: &str| DedupedFile {
+            path: PathBuf::from(path),
+            kept: PathBuf::from("/kept\tfile"),
+            kind: LinkKind::HardLink,
+            size: 4,
+            mode: 0o640,
+            modified: UNIX_EPOCH + Duration::new(1_700_000_000, 5),
+            accessed: UNIX_EPOCH,
+        };
+        let log = [
+            log_line("linked", &file("/a")),
+            log_line("linked", &file("/b")),
+            log_line("linked", &file("/c")),
+            log_line("restored", &file("/a")),
+            log_line("kept", &file("/c")),
+            // Written before the log kept enough to undo
+            "2026-01-01T00:00:00\tlinked\thardlink\t/d\t/kept".to_string(),
+        ]
+        .join("\n");
+
+        let open = open_links(&log);
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].path, Path::new("/b"));
+        assert_eq!(open[0].kept, Path::new("/kept\tfile"));
+        assert_eq!(open[0].mode, 0o640);
+        assert_eq!(open[0].modified, file("/b").modified);
+        // Nothing at those paths any more
+        assert!(!still_linked(&open[0]));
+    }
+}
@@ -0,0 +1,459 @@
+use crate::scanner::{DirEntry, ScanProgress, ScanTree};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_derive::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Version of ncdu's JSON export format that is written and understood
+const NCDU_MAJOR: u64 = 1;
+const NCDU_MINOR: u64 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Ncdu,
+}
+
+impl ExportFormat {
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Ncdu => "ncdu",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ncdu => "ncdu.json",
+        }
+    }
+}
+
+/// Where an export of `dir` is written: ~/diskord-<folder>-<date>.<extension>
+pub fn export_path(dir: &Path, format: ExportFormat) -> PathBuf {
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push(format!("diskord-{}-{}.{}", name, stamp, format.extension()));
+    path
+}
+
+/// Writes `dir` and everything below it to `out`, returning the number of entries written.
+///
+/// JSON and CSV carry the totals of every folder, the ncdu format only each
+/// entry's own size, like ncdu itself writes it.
+pub fn export(tree: &ScanTree, dir: &Path, format: ExportFormat, out: &Path) -> io::Result<u64> {
+    let Some(root) = tree.entry(dir) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "folder is not part of the scan"));
+    };
+    let now = chrono::Local::now().timestamp();
+    let mut w = BufWriter::new(File::create(out)?);
+    let mut count = 0;
+
+    match format {
+        ExportFormat::Json => {
+            write!(
+                w,
+                "{{\"version\":1,\"root\":{},\"exported\":{},\"tree\":",
+                json_string(&dir.to_string_lossy()),
+                now
+            )?;
+            write_json(tree, &root, &mut w, &mut count)?;
+            writeln!(w, "}}")?;
+        }
+        ExportFormat::Csv => {
+            writeln!(w, "path,type,size,disk_size,files,mtime")?;
+            write_csv(tree, &root, &mut w, &mut count)?;
+        }
+        ExportFormat::Ncdu => {
+            writeln!(
+                w,
+                "[{},{},{{\"progname\":\"diskord\",\"progver\":\"{}\",\"timestamp\":{}}},",
+                NCDU_MAJOR,
+                NCDU_MINOR,
+                env!("CARGO_PKG_VERSION"),
+                now
+            )?;
+            write_ncdu(tree, &root, &mut w, &mut count)?;
+            writeln!(w, "]")?;
+        }
+    }
+
+    w.flush()?;
+    Ok(count)
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+// Folders the scan didn't enter (other filesystems) come back as None
+fn children(tree: &ScanTree, entry: &DirEntry) -> Option<Vec<DirEntry>> {
+    entry.is_dir.then(|| tree.entries(&entry.path)).flatten()
+}
+
+fn write_json(tree: &ScanTree, entry: &DirEntry, w: &mut impl Write, count: &mut u64) -> io::Result<()> {
+    *count += 1;
+    write!(
+        w,
+        "{{\"name\":{},\"type\":\"{}\",\"size\":{},\"disk_size\":{},\"files\":{},\"mtime\":{}",
+        json_string(&entry.name),
+        if entry.is_dir { "dir" } else { "file" },
+        entry.size,
+        entry.disk_size,
+        entry.file_count,
+        entry.mtime
+    )?;
+    if entry.is_mount {
+        write!(w, ",\"mount\":true")?;
+    }
+    if let Some(children) = children(tree, entry) {
+        write!(w, ",\"children\":[")?;
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write_json(tree, child, w, count)?;
+        }
+        write!(w, "]")?;
+    }
+    write!(w, "}}")
+}
+
+fn write_csv(tree: &ScanTree, entry: &DirEntry, w: &mut impl Write, count: &mut u64) -> io::Result<()> {
+    *count += 1;
+    let mut path = entry.path.to_string_lossy().to_string();
+    if path.contains([',', '"', '\n', '\r']) {
+        path = format!("\"{}\"", path.replace('"', "\"\""));
+    }
+    writeln!(
+        w,
+        "{},{},{},{},{},{}",
+        path,
+        if entry.is_dir { "dir" } else { "file" },
+        entry.size,
+        entry.disk_size,
+        entry.file_count,
+        entry.mtime
+    )?;
+    for child in children(tree, entry).unwrap_or_default() {
+        write_csv(tree, &child, w, count)?;
+    }
+    Ok(())
+}
+
+// Directories are arrays of their own info followed by their children, the
+// top one is named by its full path
+fn write_ncdu(tree: &ScanTree, entry: &DirEntry, w: &mut impl Write, count: &mut u64) -> io::Result<()> {
+    *count += 1;
+    let name = if *count == 1 {
+        entry.path.to_string_lossy().to_string()
+    } else {
+        entry.name.clone()
+    };
+
+    match children(tree, entry) {
+        Some(children) => {
+            let own_size = entry.size.saturating_sub(children.iter().map(|c| c.size).sum());
+            let own_disk_size =
+                entry.disk_size.saturating_sub(children.iter().map(|c| c.disk_size).sum());
+            write!(
+                w,
+                "[{{\"name\":{},\"asize\":{},\"dsize\":{},\"mtime\":{}}}",
+                json_string(&name),
+                own_size,
+                own_disk_size,
+                entry.mtime
+            )?;
+            for child in &children {
+                writeln!(w, ",")?;
+                write_ncdu(tree, child, w, count)?;
+            }
+            write!(w, "]")
+        }
+        None if entry.is_dir => {
+            write!(w, "{{\"name\":{},\"excluded\":\"otherfs\"}}", json_string(&name))
+        }
+        None => write!(
+            w,
+            "{{\"name\":{},\"asize\":{},\"dsize\":{},\"mtime\":{}}}",
+            json_string(&name),
+            entry.size,
+            entry.disk_size,
+            entry.mtime
+        ),
+    }
+}
+
+/// Reads an export written by `ncdu -o` (or the ncdu export above) into a
+/// tree to browse, without touching anything it mentions on this machine.
+pub fn import_ncdu(path: &Path) -> Result<(ScanTree, ScanProgress), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut import = Import {
+        tree: None,
+        seen_links: HashSet::new(),
+        progress: ScanProgress::default(),
+    };
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    ExportSeed(&mut import)
+        .deserialize(&mut deserializer)
+        .map_err(|e| e.to_string())?;
+
+    match import.tree {
+        Some(tree) => Ok((tree, import.progress)),
+        None => Err("the export holds no directory".to_string()),
+    }
+}
+
+// What ncdu records about every entry; directories carry it as the first
+// element of their array
+#[derive(Deserialize)]
+struct NcduInfo {
+    name: String,
+    #[serde(default)]
+    asize: u64,
+    #[serde(default)]
+    dsize: u64,
+    #[serde(default)]
+    mtime: i64,
+    dev: Option<u64>,
+    #[serde(default)]
+    ino: u64,
+    #[serde(default)]
+    hlnkc: bool,
+    #[serde(default)]
+    nlink: u64,
+    excluded: Option<String>,
+    #[serde(default)]
+    notreg: bool,
+}
+
+struct Import {
+    tree: Option<ScanTree>,
+    // (device, inode) of hard-linked files already counted
+    seen_links: HashSet<(u64, u64)>,
+    progress: ScanProgress,
+}
+
+impl Import {
+    // None for the top directory, which becomes the tree's root
+    fn add(&mut self, parent: Option<(&Path, u64)>, info: &NcduInfo, is_dir: bool) -> Option<(PathBuf, u64)> {
+        let dev = info.dev.or(parent.map(|(_, dev)| dev)).unwrap_or(0);
+        let path = match parent {
+            None => PathBuf::from(&info.name),
+            // Only plain names, a crafted export shouldn't point anywhere else
+            Some(_) if info.name.is_empty() || info.name.contains('/') || info.name == ".." || info.name == "." => {
+                return None;
+            }
+            Some((parent, _)) => parent.join(&info.name),
+        };
+
+        let mut entry = DirEntry {
+            path: path.clone(),
+            name: info.name.clone(),
+            size: info.asize,
+            disk_size: info.dsize,
+            is_dir,
+            shared: false,
+            is_mount: false,
+            file_count: u64::from(!is_dir),
+            mtime: info.mtime,
+            atime: 0,
+        };
+        match info.excluded.as_deref() {
+            // Another filesystem ncdu didn't enter, shown as an empty mount point
+            Some("otherfs") | Some("kernfs") => {
+                entry.is_dir = true;
+                entry.is_mount = true;
+                entry.size = 0;
+                entry.disk_size = 0;
+                entry.file_count = 0;
+            }
+            Some(_) => return None,
+            None if info.notreg => return None,
+            None => {}
+        }
+        if !is_dir && (info.hlnkc || info.nlink > 1) && !self.seen_links.insert((dev, info.ino)) {
+            // Already counted through another link
+            entry.size = 0;
+            entry.disk_size = 0;
+        }
+
+        if parent.is_none() {
+            self.tree = Some(ScanTree::new(path.clone()));
+        }
+        let tree = self.tree.as_mut()?;
+        if !tree.add_entry(&entry) {
+            return None;
+        }
+        if !entry.is_dir {
+            self.progress.files += 1;
+        }
+        self.progress.bytes += entry.size;
+        self.progress.disk_bytes += entry.disk_size;
+        Some((path, dev))
+    }
+}
+
+// [major, minor, {metadata}, root directory]
+struct ExportSeed<'a>(&'a mut Import);
+
+impl<'de> DeserializeSeed<'de> for ExportSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ExportSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an ncdu JSON export")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let major: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("missing format version"))?;
+        if major != NCDU_MAJOR {
+            return Err(de::Error::custom(format!("unsupported ncdu export version {}", major)));
+        }
+        seq.next_element::<IgnoredAny>()?; // minor version
+        seq.next_element::<IgnoredAny>()?; // metadata
+        seq.next_element_seed(NodeSeed {
+            import: self.0,
+            parent: None,
+        })?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+// A file (an object) or a directory (an array) below `parent`
+struct NodeSeed<'a> {
+    import: &'a mut Import,
+    parent: Option<(&'a Path, u64)>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an ncdu file or directory entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        let info = NcduInfo::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        if self.parent.is_none() {
+            return Err(de::Error::custom("the export's top entry is not a directory"));
+        }
+        self.import.add(self.parent, &info, false);
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let info: NcduInfo = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("directory without info"))?;
+        let added = self.import.add(self.parent, &info, true);
+        if self.parent.is_none() && added.is_none() {
+            return Err(de::Error::custom("unreadable top directory"));
+        }
+
+        // Children of a directory that couldn't be added are skipped along with it
+        let mount = added.as_ref().is_some_and(|_| info.excluded.is_some());
+        match added.filter(|_| !mount) {
+            Some((path, dev)) => {
+                while seq
+                    .next_element_seed(NodeSeed {
+                        import: &mut *self.import,
+                        parent: Some((&path, dev)),
+                    })?
+                    .is_some()
+                {}
+            }
+            None => while seq.next_element::<IgnoredAny>()?.is_some() {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::SizeMode;
+    use std::fs;
+
+    fn import(name: &str, json: &str) -> Result<(ScanTree, ScanProgress), String> {
+        let path = std::env::temp_dir().join(format!("diskord-{}-{}.json", name, std::process::id()));
+        fs::write(&path, json).unwrap();
+        let result = import_ncdu(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn names(tree: &ScanTree, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = tree.entries(Path::new(dir)).unwrap().into_iter().map(|e| e.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn skips_names_that_are_not_plain() {
+        let json = r#"[1, 2, {"progname": "ncdu"}, [{"name": "/srv", "dev": 1},
+            {"name": "ok", "asize": 10, "dsize": 4096},
+            {"name": "../escape", "asize": 10, "dsize": 4096},
+            {"name": "a/b", "asize": 10, "dsize": 4096},
+            {"name": "..", "asize": 10, "dsize": 4096},
+            {"name": ".", "asize": 10, "dsize": 4096},
+            {"name": "", "asize": 10, "dsize": 4096},
+            [{"name": ".."}, {"name": "inside", "asize": 10, "dsize": 4096}],
+            [{"name": "sub"}, {"name": "inside", "asize": 10, "dsize": 4096}]]]"#;
+        let (tree, progress) = import("ncdu-names", json).unwrap();
+        assert_eq!(names(&tree, "/srv"), ["ok", "sub"]);
+        assert_eq!(names(&tree, "/srv/sub"), ["inside"]);
+        assert_eq!(progress.files, 2);
+        assert_eq!(tree.dir_size(Path::new("/srv"), SizeMode::Apparent), Some(20));
+    }
+
+    #[test]
+    fn counts_hard_links_once() {
+        let json = r#"[1, 2, {"progname": "ncdu"}, [{"name": "/srv", "dev": 1},
+            {"name": "a", "asize": 100, "dsize": 4096, "ino": 7, "hlnkc": true},
+            [{"name": "sub"}, {"name": "b", "asize": 100, "dsize": 4096, "ino": 7, "nlink": 2}],
+            [{"name": "other", "dev": 2}, {"name": "c", "asize": 100, "dsize": 4096, "ino": 7, "nlink": 2}]]]"#;
+        let (tree, progress) = import("ncdu-links", json).unwrap();
+        // The same inode on another device is a different file
+        assert_eq!(tree.dir_size(Path::new("/srv"), SizeMode::Apparent), Some(200));
+        assert_eq!(tree.dir_size(Path::new("/srv/sub"), SizeMode::Apparent), Some(0));
+        assert_eq!(progress.files, 3);
+        assert_eq!(progress.bytes, 200);
+    }
+
+    #[test]
+    fn rejects_exports_without_a_directory() {
+        assert!(import("ncdu-empty", r#"[1, 2, {"progname": "ncdu"}]"#).is_err());
+        assert!(import("ncdu-garbage", "not json").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::scanner::SizeMode;
+
+/// Broad kind of file, judged by its extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    Video,
+    Images,
+    Audio,
+    Archives,
+    Documents,
+    Code,
+    Binaries,
+    VmDisks,
+    Other,
+}
+
+impl Category {
+    /// `extension` is expected in lowercase, without the dot
+    pub fn of(extension: &str) -> Self {
+        match extension {
+            "mkv" | "mp4" | "m4v" | "avi" | "mov" | "webm" | "wmv" | "flv" | "mpg" | "mpeg"
+            | "ts" | "m2ts" | "3gp" => Category::Video,
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "tif" | "tiff" | "svg" | "heic"
+            | "avif" | "ico" | "raw" | "cr2" | "nef" | "arw" | "dng" | "psd" | "xcf" | "kra" => {
+                Category::Images
+            }
+            "mp3" | "flac" | "wav" | "ogg" | "opus" | "m4a" | "aac" | "wma" | "aiff" => {
+                Category::Audio
+            }
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "txz" | "bz2" | "zst" | "7z" | "rar" | "lz4"
+            | "lzma" | "cab" | "deb" | "rpm" | "pkg" | "jar" | "whl" | "crate" => Category::Archives,
+            "pdf" | "doc" | "docx" | "odt" | "xls" | "xlsx" | "ods" | "ppt" | "pptx" | "odp"
+            | "epub" | "txt" | "md" | "csv" | "rtf" | "tex" => Category::Documents,
+            "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "py" | "js" | "mjs" | "tsx" | "jsx"
+            | "go" | "java" | "kt" | "rb" | "php" | "lua" | "sh" | "zsh" | "fish" | "html"
+            | "css" | "scss" | "json" | "toml" | "yaml" | "yml" | "xml" | "sql" | "zig" | "swift"
+            | "cs" | "hs" | "ex" | "exs" | "vue" | "svelte" | "ipynb" => Category::Code,
+            "so" | "a" | "o" | "rlib" | "rmeta" | "dll" | "exe" | "bin" | "elf" | "appimage"
+            | "class" | "pyc" | "wasm" | "dylib" | "node" => Category::Binaries,
+            "qcow2" | "qcow" | "vdi" | "vmdk" | "vhd" | "vhdx" | "img" | "iso" | "ova" | "ovf" => {
+                Category::VmDisks
+            }
+            _ => Category::Other,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Video => "Video",
+            Category::Images => "Images",
+            Category::Audio => "Audio",
+            Category::Archives => "Archives",
+            Category::Documents => "Documents",
+            Category::Code => "Code",
+            Category::Binaries => "Binaries",
+            Category::VmDisks => "VM disks",
+            Category::Other => "Other",
+        }
+    }
+}
+
+/// Files sharing an extension (or category), summed up
+#[derive(Clone, Debug, Default)]
+pub struct TypeTotals {
+    pub size: u64,
+    pub disk_size: u64,
+    pub files: u64,
+}
+
+impl TypeTotals {
+    pub fn add(&mut self, other: &TypeTotals) {
+        self.size += other.size;
+        self.disk_size += other.disk_size;
+        self.files += other.files;
+    }
+
+    pub fn size_in(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Allocated => self.disk_size,
+            SizeMode::Apparent => self.size,
+        }
+    }
+}
+
+/// The lowercased extension used to group a file, "(none)" when it has none
+pub fn extension_of(name: &std::ffi::OsStr) -> String {
+    std::path::Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "(none)".to_string())
+}
@@ -0,0 +1,290 @@
+use crate::scanner::{DirEntry, SizeMode};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// First line of the index file, bumped whenever the line format changes
+const INDEX_HEADER: &str = "# diskord scan index v1";
+
+// Smaller folders are left out to keep the file small, a folder missing from
+// the index simply counts as having been (close to) empty
+const MIN_INDEXED_SIZE: u64 = 1024 * 1024;
+
+// A folder whose change comes almost entirely from one of its subfolders is
+// left out of the changes list, the subfolder tells the same story
+const DOMINANT_SHARE_PERCENT: u128 = 90;
+
+// Scans finishing close together would otherwise overwrite each other's updates
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// What a folder held when it was last scanned
+#[derive(Clone, Copy, Debug)]
+pub struct IndexEntry {
+    pub size: u64,
+    pub disk_size: u64,
+    pub files: u64,
+    pub mtime: i64,
+    /// When the scan that saw it finished, as a unix timestamp
+    pub scanned: i64,
+}
+
+impl IndexEntry {
+    pub fn size_in(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Allocated => self.disk_size,
+            SizeMode::Apparent => self.size,
+        }
+    }
+}
+
+/// Folder totals from earlier Deep Scanner runs, by path
+pub type ScanIndex = HashMap<PathBuf, IndexEntry>;
+
+/// A folder that grew or shrank since the previous scan
+#[derive(Clone, Debug)]
+pub struct Change {
+    /// The folder as it is now, empty if it is gone
+    pub entry: DirEntry,
+    pub delta: i64,
+    pub gone: bool,
+}
+
+/// ~/.cache/diskord/index.tsv, or wherever $XDG_CACHE_HOME points
+pub fn index_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|mut p| {
+        p.push("diskord");
+        p.push("index.tsv");
+        p
+    })
+}
+
+pub fn load() -> ScanIndex {
+    let Some(content) = index_path().and_then(|p| fs::read(p).ok()) else {
+        return ScanIndex::new();
+    };
+    if !content.starts_with(INDEX_HEADER.as_bytes()) {
+        return ScanIndex::new();
+    }
+    content.split(|&b| b == b'\n').filter_map(parse_line).collect()
+}
+
+// size, disk size, files, mtime, scan time and the path, tab separated
+fn parse_line(line: &[u8]) -> Option<(PathBuf, IndexEntry)> {
+    let mut fields = line.splitn(6, |&b| b == b'\t');
+    let mut number = || -> Option<i64> { std::str::from_utf8(fields.next()?).ok()?.parse().ok() };
+    let entry = IndexEntry {
+        size: number()? as u64,
+        disk_size: number()? as u64,
+        files: number()? as u64,
+        mtime: number()?,
+        scanned: number()?,
+    };
+    let path = fields.next().filter(|p| p.starts_with(b"/"))?;
+    Some((PathBuf::from(OsStr::from_bytes(path)), entry))
+}
+
+/// Replaces everything the index knows below `root` with `folders` from a
+/// fresh scan, and hands back the index as it was before.
+pub fn update(root: &Path, folders: &[DirEntry], scanned: i64) -> io::Result<ScanIndex> {
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = load();
+    let Some(path) = index_path() else {
+        return Ok(previous);
+    };
+
+    let mut out = Vec::new();
+    writeln!(out, "{}", INDEX_HEADER)?;
+    let kept = previous.iter().filter(|(path, _)| !path.starts_with(root));
+    let fresh = folders
+        .iter()
+        .filter(|f| f.size.max(f.disk_size) >= MIN_INDEXED_SIZE)
+        .map(|f| {
+            let entry = IndexEntry {
+                size: f.size,
+                disk_size: f.disk_size,
+                files: f.file_count,
+                mtime: f.mtime,
+                scanned,
+            };
+            (&f.path, entry)
+        });
+    for (folder, entry) in kept.map(|(p, e)| (p, *e)).chain(fresh) {
+        let bytes = folder.as_os_str().as_bytes();
+        // The format is line based, such names can't be stored
+        if bytes.contains(&b'\n') {
+            continue;
+        }
+        write!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t",
+            entry.size, entry.disk_size, entry.files, entry.mtime, entry.scanned
+        )?;
+        out.extend_from_slice(bytes);
+        out.push(b'\n');
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tsv.tmp");
+    fs::write(&tmp, out)?;
+    fs::rename(&tmp, &path)?;
+    Ok(previous)
+}
+
+/// Subfolders of `dir` whose size changed between `previous` and `current`
+/// (every folder below `dir` as it is now), biggest change first. Folders that
+/// disappeared are listed with their old size as the loss.
+pub fn changes(previous: &ScanIndex, current: &[DirEntry], dir: &Path, mode: SizeMode) -> Vec<Change> {
+    let old_size = |path: &Path| previous.get(path).map_or(0, |e| e.size_in(mode)) as i64;
+    let current_paths: HashSet<&Path> = current.iter().map(|e| e.path.as_path()).collect();
+
+    let mut changes: Vec<Change> = current
+        .iter()
+        .filter(|e| e.path != dir)
+        // Too small to have been indexed, there's nothing to compare with
+        .filter(|e| previous.contains_key(&e.path) || e.size.max(e.disk_size) >= MIN_INDEXED_SIZE)
+        .map(|e| Change {
+            entry: e.clone(),
+            delta: e.size_in(mode) as i64 - old_size(&e.path),
+            gone: false,
+        })
+        .collect();
+
+    // Only the topmost of a removed tree, its subfolders went with it
+    for (path, old) in previous {
+        if path.starts_with(dir)
+            && path != dir
+            && !current_paths.contains(path.as_path())
+            && path.parent().is_some_and(|p| current_paths.contains(p))
+        {
+            changes.push(Change {
+                entry: DirEntry {
+                    path: path.clone(),
+                    name: String::new(),
+                    size: 0,
+                    disk_size: 0,
+                    is_dir: true,
+                    shared: false,
+                    is_mount: false,
+                    file_count: 0,
+                    mtime: old.mtime,
+                    atime: 0,
+                },
+                delta: -(old.size_in(mode) as i64),
+                gone: true,
+            });
+        }
+    }
+    changes.retain(|c| c.delta != 0);
+
+    // The biggest change in each direction among every folder's subfolders
+    let mut biggest_child: HashMap<(&Path, bool), i64> = HashMap::new();
+    for change in &changes {
+        if let Some(parent) = change.entry.path.parent() {
+            let biggest = biggest_child.entry((parent, change.delta > 0)).or_insert(0);
+            if change.delta.abs() > biggest.abs() {
+                *biggest = change.delta;
+            }
+        }
+    }
+    let explained_by_child: HashSet<PathBuf> = changes
+        .iter()
+        .filter(|c| {
+            biggest_child
+                .get(&(c.entry.path.as_path(), c.delta > 0))
+                .is_some_and(|&child| {
+                    child.unsigned_abs() as u128 * 100
+                        >= c.delta.unsigned_abs() as u128 * DOMINANT_SHARE_PERCENT
+                })
+        })
+        .map(|c| c.entry.path.clone())
+        .collect();
+    changes.retain(|c| !explained_by_child.contains(&c.entry.path));
+
+    for change in &mut changes {
+        change.entry.name = change
+            .entry
+            .path
+            .strip_prefix(dir)
+            .unwrap_or(&change.entry.path)
+            .to_string_lossy()
+            .to_string();
+    }
+    changes.sort_by_key(|c| std::cmp::Reverse(c.delta.unsigned_abs()));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn entry(path: &str, size: u64) -> DirEntry {
+        DirEntry {
+            path: PathBuf::from(path),
+            name: String::new(),
+            size,
+            disk_size: size,
+            is_dir: true,
+            shared: false,
+            is_mount: false,
+            file_count: 1,
+            mtime: 0,
+            atime: 0,
+        }
+    }
+
+    fn indexed(entries: &[(&str, u64)]) -> ScanIndex {
+        entries
+            .iter()
+            .map(|&(path, size)| {
+                let entry = IndexEntry { size, disk_size: size, files: 1, mtime: 0, scanned: 0 };
+                (PathBuf::from(path), entry)
+            })
+            .collect()
+    }
+
+    fn names(changes: &[Change]) -> Vec<(&str, i64)> {
+        changes.iter().map(|c| (c.entry.name.as_str(), c.delta / MB as i64)).collect()
+    }
+
+    #[test]
+    fn leaves_out_folders_a_subfolder_explains() {
+        let previous = indexed(&[
+            ("/d", 100 * MB),
+            ("/d/a", 10 * MB),
+            ("/d/a/big", 5 * MB),
+            ("/d/b", 40 * MB),
+            ("/d/b/x", 10 * MB),
+            ("/d/b/y", 10 * MB),
+        ]);
+        let current = [
+            entry("/d", 220 * MB),
+            // Grew by 100, 95 of it in one subfolder
+            entry("/d/a", 110 * MB),
+            entry("/d/a/big", 100 * MB),
+            // Grew by 20, split evenly
+            entry("/d/b", 60 * MB),
+            entry("/d/b/x", 20 * MB),
+            entry("/d/b/y", 20 * MB),
+        ];
+        let changes = changes(&previous, &current, Path::new("/d"), SizeMode::Allocated);
+        assert_eq!(names(&changes), [("a/big", 95), ("b", 20), ("b/x", 10), ("b/y", 10)]);
+    }
+
+    #[test]
+    fn a_subfolder_growing_does_not_explain_a_shrinking_parent() {
+        let previous = indexed(&[("/d/a", 100 * MB), ("/d/a/grew", 10 * MB), ("/d/a/gone", 80 * MB)]);
+        let current = [entry("/d", 40 * MB), entry("/d/a", 40 * MB), entry("/d/a/grew", 30 * MB)];
+        let changes = changes(&previous, &current, Path::new("/d"), SizeMode::Allocated);
+        // The loss is explained by the removed folder alone
+        assert_eq!(names(&changes), [("a/gone", -80), ("a/grew", 20)]);
+        assert!(changes[0].gone);
+    }
+}
@@ -7,9 +7,176 @@ use ratatui::{backend::CrosstermBackend, prelude::*, widgets::*, Terminal};
 use std::io::{stdout, Result};
 use std::time::Duration;
 
+pub mod config;
+pub mod duplicates;
+pub mod export;
+pub mod filetypes;
+pub mod index;
 pub mod scanner;
 pub mod system;
 pub mod theme;
+pub mod trash;
+pub mod treemap;
+
+// How many entries stay visible when smaller items are collapsed into one row
+const COLLAPSED_ROWS: usize = 50;
+
+// How many files the "largest files" view lists
+const LARGEST_FILES_LIMIT: usize = 200;
+
+// What the Deep Scanner list is showing for the current folder
+#[derive(PartialEq, Clone, Copy)]
+enum ScannerMode {
+    Folders,
+    Treemap,
+    LargestFiles,
+    Duplicates,
+    Stale,
+    Empty,
+    Types,
+    Changes,
+}
+
+impl ScannerMode {
+    fn next(self) -> Self {
+        match self {
+            ScannerMode::Folders => ScannerMode::Treemap,
+            ScannerMode::Treemap => ScannerMode::LargestFiles,
+            ScannerMode::LargestFiles => ScannerMode::Duplicates,
+            ScannerMode::Duplicates => ScannerMode::Stale,
+            ScannerMode::Stale => ScannerMode::Empty,
+            ScannerMode::Empty => ScannerMode::Types,
+            ScannerMode::Types => ScannerMode::Changes,
+            ScannerMode::Changes => ScannerMode::Folders,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            ScannerMode::Folders => ScannerMode::Changes,
+            ScannerMode::Treemap => ScannerMode::Folders,
+            ScannerMode::LargestFiles => ScannerMode::Treemap,
+            ScannerMode::Duplicates => ScannerMode::LargestFiles,
+            ScannerMode::Stale => ScannerMode::Duplicates,
+            ScannerMode::Empty => ScannerMode::Stale,
+            ScannerMode::Types => ScannerMode::Empty,
+            ScannerMode::Changes => ScannerMode::Types,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ScannerMode::Folders => "Path",
+            ScannerMode::Treemap => "Treemap of",
+            ScannerMode::LargestFiles => "Largest files under",
+            ScannerMode::Duplicates => "Duplicates under",
+            ScannerMode::Stale => "Unused files under",
+            ScannerMode::Empty => "Empty folders and files under",
+            ScannerMode::Types => "File types under",
+            ScannerMode::Changes => "Changes since the last scan under",
+        }
+    }
+
+    // Views whose rows keep their own order (groups, biggest change first) instead of the sort key
+    fn keeps_own_order(self) -> bool {
+        matches!(
+            self,
+            ScannerMode::Treemap
+                | ScannerMode::Duplicates
+                | ScannerMode::Stale
+                | ScannerMode::Changes
+        )
+    }
+
+    // Views that list files from all over the tree rather than one folder's children
+    fn lists_files(self) -> bool {
+        matches!(
+            self,
+            ScannerMode::LargestFiles
+                | ScannerMode::Duplicates
+                | ScannerMode::Stale
+                | ScannerMode::Empty
+                | ScannerMode::Changes
+        )
+    }
+}
+
+// How long ago something must have been trashed to show up in the trash browser
+#[derive(PartialEq, Clone, Copy)]
+enum TrashAge {
+    Any,
+    Day,
+    Week,
+    Month,
+    Older,
+}
+
+impl TrashAge {
+    fn next(self) -> Self {
+        match self {
+            TrashAge::Any => TrashAge::Day,
+            TrashAge::Day => TrashAge::Week,
+            TrashAge::Week => TrashAge::Month,
+            TrashAge::Month => TrashAge::Older,
+            TrashAge::Older => TrashAge::Any,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TrashAge::Any => "any time",
+            TrashAge::Day => "in the last 24 hours",
+            TrashAge::Week => "in the last 7 days",
+            TrashAge::Month => "in the last 30 days",
+            TrashAge::Older => "more than 30 days ago",
+        }
+    }
+
+    // Items without a deletion date only show up when not filtering by age
+    fn includes(self, deleted: Option<chrono::NaiveDateTime>, now: chrono::NaiveDateTime) -> bool {
+        let Some(deleted) = deleted else {
+            return self == TrashAge::Any;
+        };
+        let age = now - deleted;
+        match self {
+            TrashAge::Any => true,
+            TrashAge::Day => age <= chrono::Duration::days(1),
+            TrashAge::Week => age <= chrono::Duration::days(7),
+            TrashAge::Month => age <= chrono::Duration::days(30),
+            TrashAge::Older => age > chrono::Duration::days(30),
+        }
+    }
+}
+
+// What a batch of links did so far, summed up once it's done
+#[derive(Default)]
+struct LinkTally {
+    linked: usize,
+    hard_linked: usize,
+    freed: u64,
+    failed: Option<String>,
+}
+
+impl LinkTally {
+    fn message(&self) -> String {
+        let mut message = format!(
+            "Linked {} copies, about {} freed",
+            self.linked,
+            system::format_bytes(self.freed)
+        );
+        if self.hard_linked > 0 {
+            // Unlike reflinks, these don't stay independent copies
+            message.push_str(&format!(
+                ", {} of them as hard links (no reflinks here), writing to one path changes the other",
+                self.hard_linked
+            ));
+        }
+        if let Some(failed) = &self.failed {
+            message.push_str(&format!(", failed on {}", failed));
+        }
+        message
+    }
+}
 
 #[derive(PartialEq, Clone, Copy)]
 enum ActiveTab {
@@ -23,6 +190,7 @@ enum ActiveTab {
 
 struct App {
     theme: theme::OmarchyTheme,
+    config: config::DiskordConfig,
     should_quit: bool,
     disks: Vec<system::DiskUsage>,
 
@@ -51,6 +219,15 @@ struct App {
     clean_cargo: bool,
     clean_npm: bool,
 
+    // Build artifacts under config.project_roots, searched for in the background
+    // the first time the Developer tab is opened and listed below the caches once
+    // that is done. Selection is keyed by the first path.
+    build_artifacts: Vec<system::BuildArtifact>,
+    selected_artifacts: std::collections::HashSet<std::path::PathBuf>,
+    artifacts_rx: Option<std::sync::mpsc::Receiver<Vec<system::BuildArtifact>>>,
+    artifacts_searched: bool,
+    artifacts_clean_rx: Option<std::sync::mpsc::Receiver<system::ArtifactUpdate>>,
+
     // Apps Targets (Read-only for now, just to show sizes, or we could add wipe buttons)
     steam_size: u64,
     flatpak_size: u64,
@@ -60,17 +237,89 @@ struct App {
     dev_index: usize,
     apps_index: usize,
     scanner_index: usize,
+    // First visible Deep Scanner row, kept between frames so scrolling is smooth
+    scanner_offset: std::cell::Cell<usize>,
+    // Where the treemap was last drawn, moving between its rectangles lays it out again
+    treemap_area: std::cell::Cell<ratatui::layout::Rect>,
 
     // Scanner State
+    scanner_mode: ScannerMode,
     current_scan_path: std::path::PathBuf,
     scan_results: Vec<scanner::DirEntry>,
     selected_paths: std::collections::HashSet<std::path::PathBuf>,
+    scan_tree: Option<scanner::ScanTree>,
+    scan_job: Option<scanner::ScanJob>,
+    scan_progress: scanner::ScanProgress,
+    scan_cancelled: bool,
+    size_mode: scanner::SizeMode,
+    // Fold everything past the largest entries into a single "N smaller items" row
+    collapse_small: bool,
+    sort_key: scanner::SortKey,
+    sort_descending: bool,
+    // Live `/` filter over scan_results; scan_view holds the indices that pass it
+    search_query: String,
+    search_input: bool,
+    scan_view: Vec<usize>,
+    // None follows the default: stay on one filesystem when scanning /
+    one_filesystem: Option<bool>,
+    // The ncdu export being browsed instead of the local disk
+    imported_from: Option<std::path::PathBuf>,
+    // Waiting for the export format key
+    export_prompt: bool,
+    // Outcome of the last export or refused action, shown in the status line
+    scanner_message: Option<String>,
+    // Maps each scan_results entry to its group in the duplicates and stale views
+    row_group: Vec<usize>,
+    // Duplicate search results for duplicate_root
+    duplicate_groups: Vec<duplicates::DuplicateGroup>,
+    duplicate_root: Option<std::path::PathBuf>,
+    duplicate_job: Option<duplicates::DuplicateJob>,
+    duplicate_progress: duplicates::DuplicateProgress,
+    duplicates_cancelled: bool,
+    // Outcome of the last in-place deduplication, shown in the status line
+    dedupe_message: Option<String>,
+    link_job: Option<duplicates::LinkJob>,
+    link_tally: LinkTally,
+    // Undoing a link copies the whole file, so it runs in the background too
+    unlink_job: Option<duplicates::LinkJob>,
+    // Files nobody modified or read for this many months count as stale
+    stale_months: u32,
+    stale_groups: Vec<scanner::StaleGroup>,
+    // Per-category totals shown next to the file types view, biggest first
+    type_categories: Vec<(filetypes::Category, filetypes::TypeTotals)>,
+    // What the treemap colours each entry by
+    treemap_colors: std::collections::HashMap<std::path::PathBuf, filetypes::Category>,
+    // Folder totals from before this session, what the changes view compares
+    // against. Read back while the first scan of the session gets saved.
+    scan_index: Option<index::ScanIndex>,
+    // Each index update sends back the folder it covered and the index as it was before
+    index_tx: std::sync::mpsc::Sender<(std::path::PathBuf, index::ScanIndex)>,
+    index_rx: std::sync::mpsc::Receiver<(std::path::PathBuf, index::ScanIndex)>,
+    // One per row in the changes view
+    scan_changes: Vec<index::Change>,
 
     // Session Trash State
     trashed_items: Vec<system::TrashedItem>,
+    // Selected paths still being trashed, and how far a copy across filesystems has got
+    trash_rx: Option<std::sync::mpsc::Receiver<system::TrashUpdate>>,
+    trash_progress: Option<(std::path::PathBuf, trash::MoveProgress)>,
+    // Duplicates replaced by links this session, listed after the trashed items
+    deduped_items: Vec<duplicates::DedupedFile>,
     session_trash_index: usize,
     show_root_warning: bool,
 
+    // Whole Trash Browser State, everything in the home and per-mount trashes
+    browse_trash: bool,
+    trash_entries: Vec<trash::TrashEntry>,
+    trash_entries_rx: Option<std::sync::mpsc::Receiver<Vec<trash::TrashEntry>>>,
+    // Indices into trash_entries that pass the location and age filters
+    trash_view: Vec<usize>,
+    trash_browser_index: usize,
+    trash_filter: String,
+    trash_filter_input: bool,
+    trash_age: TrashAge,
+    trash_message: Option<String>,
+
     // Snapshots State
     snapshots: Vec<system::Snapshot>,
     snapshots_index: usize,
@@ -84,10 +333,21 @@ impl App {
     fn new() -> Self {
         let orphaned = system::get_orphaned_packages();
         let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("~"));
-        let initial_scan = scanner::scan_directory(&home);
+        let config = config::DiskordConfig::load();
+        let stale_months = config.stale_months;
+
+        let (index_tx, index_rx) = std::sync::mpsc::channel();
+        let initial_scan = scanner::spawn_scan(
+            &home,
+            scanner::ScanOptions {
+                exclude: std::sync::Arc::new(config.exclude.clone()),
+                ..Default::default()
+            },
+        );
 
         Self {
             theme: theme::OmarchyTheme::load(),
+            config,
             should_quit: false,
             disks: system::get_disks(),
 
@@ -114,6 +374,12 @@ impl App {
             clean_cargo: false,
             clean_npm: false,
 
+            build_artifacts: Vec::new(),
+            selected_artifacts: std::collections::HashSet::new(),
+            artifacts_rx: None,
+            artifacts_searched: false,
+            artifacts_clean_rx: None,
+
             steam_size: system::get_steam_size(),
             flatpak_size: system::get_flatpak_size(),
 
@@ -121,15 +387,64 @@ impl App {
             dev_index: 0,
             apps_index: 0,
             scanner_index: 0,
+            scanner_offset: std::cell::Cell::new(0),
+            treemap_area: std::cell::Cell::new(ratatui::layout::Rect::default()),
 
+            scanner_mode: ScannerMode::Folders,
             current_scan_path: home,
-            scan_results: initial_scan,
+            scan_results: Vec::new(),
             selected_paths: std::collections::HashSet::new(),
+            scan_tree: None,
+            scan_job: Some(initial_scan),
+            scan_progress: scanner::ScanProgress::default(),
+            scan_cancelled: false,
+            size_mode: scanner::SizeMode::default(),
+            collapse_small: false,
+            sort_key: scanner::SortKey::default(),
+            sort_descending: true,
+            search_query: String::new(),
+            search_input: false,
+            scan_view: Vec::new(),
+            one_filesystem: None,
+            row_group: Vec::new(),
+            duplicate_groups: Vec::new(),
+            duplicate_root: None,
+            duplicate_job: None,
+            duplicate_progress: duplicates::DuplicateProgress::default(),
+            duplicates_cancelled: false,
+            dedupe_message: None,
+            link_job: None,
+            link_tally: LinkTally::default(),
+            unlink_job: None,
+            stale_months,
+            stale_groups: Vec::new(),
+            type_categories: Vec::new(),
+            treemap_colors: std::collections::HashMap::new(),
+            scan_index: None,
+            index_tx,
+            index_rx,
+            scan_changes: Vec::new(),
+            imported_from: None,
+            export_prompt: false,
+            scanner_message: None,
 
             trashed_items: Vec::new(),
+            trash_rx: None,
+            trash_progress: None,
+            deduped_items: duplicates::logged_links(),
             session_trash_index: 0,
             show_root_warning: false,
 
+            browse_trash: false,
+            trash_entries: Vec::new(),
+            trash_entries_rx: None,
+            trash_view: Vec::new(),
+            trash_browser_index: 0,
+            trash_filter: String::new(),
+            trash_filter_input: false,
+            trash_age: TrashAge::Any,
+            trash_message: None,
+
             snapshots: Vec::new(),
             snapshots_index: 0,
             snapshots_available: system::check_snapper_available(),
@@ -154,6 +469,7 @@ impl App {
             }
             ActiveTab::Snapshots => ActiveTab::System,
         };
+        self.search_build_artifacts();
     }
 
     fn prev_tab(&mut self) {
@@ -171,22 +487,42 @@ impl App {
             ActiveTab::SessionTrash => ActiveTab::DeepScanner,
             ActiveTab::Snapshots => ActiveTab::SessionTrash,
         };
+        self.search_build_artifacts();
+    }
+
+    // Walking every project root takes a while, so it only happens once someone looks
+    fn search_build_artifacts(&mut self) {
+        if self.active_tab != ActiveTab::Developer || self.artifacts_searched {
+            return;
+        }
+        self.artifacts_searched = true;
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.artifacts_rx = Some(rx);
+        let project_roots = self.config.project_roots.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(system::find_build_artifacts(&project_roots));
+        });
     }
 
     fn next_item(&mut self) {
         match self.active_tab {
             ActiveTab::System => self.system_index = (self.system_index + 1) % 5,
-            ActiveTab::Developer => self.dev_index = (self.dev_index + 1) % 3,
+            ActiveTab::Developer => self.dev_index = (self.dev_index + 1) % self.dev_len(),
             ActiveTab::Apps => self.apps_index = (self.apps_index + 1) % 2,
             ActiveTab::DeepScanner => {
-                if !self.scan_results.is_empty() {
-                    self.scanner_index = (self.scanner_index + 1) % self.scan_results.len();
+                if self.scanner_len() > 0 {
+                    self.scanner_index = (self.scanner_index + 1) % self.scanner_len();
+                }
+            }
+            ActiveTab::SessionTrash if self.browse_trash => {
+                if !self.trash_view.is_empty() {
+                    self.trash_browser_index = (self.trash_browser_index + 1) % self.trash_view.len();
                 }
             }
             ActiveTab::SessionTrash => {
-                if !self.trashed_items.is_empty() {
+                if self.session_trash_len() > 0 {
                     self.session_trash_index =
-                        (self.session_trash_index + 1) % self.trashed_items.len();
+                        (self.session_trash_index + 1) % self.session_trash_len();
                 }
             }
             ActiveTab::Snapshots => {
@@ -210,7 +546,7 @@ impl App {
                 if self.dev_index > 0 {
                     self.dev_index -= 1;
                 } else {
-                    self.dev_index = 2;
+                    self.dev_index = self.dev_len() - 1;
                 }
             }
             ActiveTab::Apps => {
@@ -221,20 +557,29 @@ impl App {
                 }
             }
             ActiveTab::DeepScanner => {
-                if !self.scan_results.is_empty() {
+                if self.scanner_len() > 0 {
                     if self.scanner_index > 0 {
                         self.scanner_index -= 1;
                     } else {
-                        self.scanner_index = self.scan_results.len() - 1;
+                        self.scanner_index = self.scanner_len() - 1;
+                    }
+                }
+            }
+            ActiveTab::SessionTrash if self.browse_trash => {
+                if !self.trash_view.is_empty() {
+                    if self.trash_browser_index > 0 {
+                        self.trash_browser_index -= 1;
+                    } else {
+                        self.trash_browser_index = self.trash_view.len() - 1;
                     }
                 }
             }
             ActiveTab::SessionTrash => {
-                if !self.trashed_items.is_empty() {
+                if self.session_trash_len() > 0 {
                     if self.session_trash_index > 0 {
                         self.session_trash_index -= 1;
                     } else {
-                        self.session_trash_index = self.trashed_items.len() - 1;
+                        self.session_trash_index = self.session_trash_len() - 1;
                     }
                 }
             }
@@ -264,11 +609,26 @@ impl App {
                 0 => self.clean_docker = !self.clean_docker,
                 1 => self.clean_cargo = !self.clean_cargo,
                 2 => self.clean_npm = !self.clean_npm,
-                _ => {}
+                i => {
+                    if let Some(&(artifact, part)) = self.artifact_rows().get(i - 3) {
+                        let artifact = &self.build_artifacts[artifact];
+                        let key = match part {
+                            Some(part) => artifact.parts[part].paths[0].clone(),
+                            None => artifact.paths[0].clone(),
+                        };
+                        if !self.selected_artifacts.remove(&key) {
+                            self.selected_artifacts.insert(key);
+                        }
+                    }
+                }
             },
-            ActiveTab::DeepScanner => {
-                if !self.scan_results.is_empty() {
-                    let path = self.scan_results[self.scanner_index].path.clone();
+            // File type rows are summaries, there is nothing to select
+            ActiveTab::DeepScanner if self.scanner_mode != ScannerMode::Types => {
+                // Folders that are gone from disk can't be trashed again
+                if let Some(entry) = self.selected_scan_entry()
+                    && (self.scanner_mode != ScannerMode::Changes || entry.path.exists())
+                {
+                    let path = entry.path.clone();
                     if self.selected_paths.contains(&path) {
                         self.selected_paths.remove(&path);
                     } else {
@@ -280,23 +640,862 @@ impl App {
         }
     }
 
+    // Selects every entry the list currently shows (search filter included),
+    // or clears them again if they all are selected already
+    fn select_all_visible(&mut self) {
+        if self.active_tab == ActiveTab::Developer {
+            let all_selected = self
+                .build_artifacts
+                .iter()
+                .all(|a| self.selected_artifacts.contains(&a.paths[0]));
+            if all_selected {
+                self.selected_artifacts.clear();
+            } else {
+                self.selected_artifacts = self
+                    .build_artifacts
+                    .iter()
+                    .map(|a| a.paths[0].clone())
+                    .collect();
+            }
+            return;
+        }
+        if self.active_tab != ActiveTab::DeepScanner
+            || matches!(self.scanner_mode, ScannerMode::Types | ScannerMode::Changes)
+        {
+            return;
+        }
+        let visible: Vec<_> = self
+            .scan_view
+            .iter()
+            .map(|&i| self.scan_results[i].path.clone())
+            .collect();
+        if visible.iter().all(|p| self.selected_paths.contains(p)) {
+            for path in &visible {
+                self.selected_paths.remove(path);
+            }
+        } else {
+            self.selected_paths.extend(visible);
+        }
+    }
+
+    // The three cache rows followed by one row per build artifact
+    fn dev_len(&self) -> usize {
+        3 + self.artifact_rows().len()
+    }
+
+    // (artifact, part) per build artifact row, Cargo targets followed by their parts
+    fn artifact_rows(&self) -> Vec<(usize, Option<usize>)> {
+        let mut rows = Vec::new();
+        for (i, artifact) in self.build_artifacts.iter().enumerate() {
+            rows.push((i, None));
+            rows.extend((0..artifact.parts.len()).map(|part| (i, Some(part))));
+        }
+        rows
+    }
+
+    // The treemap shows every entry it has room for, small ones simply get no cell
+    fn collapses_small(&self) -> bool {
+        self.collapse_small && self.scanner_mode != ScannerMode::Treemap
+    }
+
+    // Rows in the Deep Scanner list, counting the collapsed "smaller items" row
+    fn scanner_len(&self) -> usize {
+        if self.collapses_small() && self.scan_view.len() > COLLAPSED_ROWS {
+            COLLAPSED_ROWS + 1
+        } else {
+            self.scan_view.len()
+        }
+    }
+
+    // None when the list is empty or the "smaller items" row is highlighted
+    fn selected_scan_entry(&self) -> Option<&scanner::DirEntry> {
+        if self.collapses_small() && self.scanner_index >= COLLAPSED_ROWS {
+            return None;
+        }
+        self.scan_view
+            .get(self.scanner_index)
+            .map(|&i| &self.scan_results[i])
+    }
+
+    fn update_scan_view(&mut self) {
+        self.scan_view = self
+            .scan_results
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                self.search_query.is_empty()
+                    || scanner::fuzzy_match(&self.search_query, &e.name).is_some()
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if self.scanner_index >= self.scanner_len() {
+            self.scanner_index = self.scanner_len().saturating_sub(1);
+        }
+    }
+
+    fn start_search(&mut self) {
+        if self.active_tab == ActiveTab::DeepScanner {
+            self.search_input = true;
+        }
+    }
+
+    fn edit_search(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc => {
+                self.search_input = false;
+                self.clear_search();
+                return;
+            }
+            KeyCode::Enter => {
+                // Keep the filter applied and hand the keys back to the list
+                self.search_input = false;
+                return;
+            }
+            KeyCode::Down => return self.next_item(),
+            KeyCode::Up => return self.prev_item(),
+            KeyCode::Backspace => {
+                self.search_query.pop();
+            }
+            KeyCode::Char(c) => self.search_query.push(c),
+            _ => return,
+        }
+        self.scanner_index = 0;
+        self.update_scan_view();
+    }
+
+    fn clear_search(&mut self) {
+        self.search_query.clear();
+        self.update_scan_view();
+    }
+
+    fn scroll_scanner(&mut self, delta: isize) {
+        if self.active_tab != ActiveTab::DeepScanner || self.scanner_len() == 0 {
+            return;
+        }
+        self.scanner_index = self
+            .scanner_index
+            .saturating_add_signed(delta)
+            .min(self.scanner_len() - 1);
+    }
+
+    fn toggle_collapse_small(&mut self) {
+        if self.active_tab == ActiveTab::DeepScanner {
+            self.collapse_small = !self.collapse_small;
+            self.scanner_index = self.scanner_index.min(self.scanner_len().saturating_sub(1));
+        }
+    }
+
     fn drill_down(&mut self) {
-        if self.scan_results.is_empty() {
+        if self.scanner_len() == 0 {
             return;
         }
-        let selected = &self.scan_results[self.scanner_index];
-        if selected.is_dir {
-            self.current_scan_path = selected.path.clone();
-            self.scan_results = scanner::scan_directory(&self.current_scan_path);
-            self.scanner_index = 0;
+        let Some(selected) = self.selected_scan_entry() else {
+            // Opening the "smaller items" row expands it in place
+            self.toggle_collapse_small();
+            return;
+        };
+        if self.scanner_mode.lists_files() {
+            // Jump to the folder holding the file, with the file highlighted
+            let path = selected.path.clone();
+            self.scanner_mode = ScannerMode::Folders;
+            if let Some(parent) = path.parent() {
+                self.open_scan_path(parent.to_path_buf());
+            }
+            if let Some(pos) = self
+                .scan_view
+                .iter()
+                .position(|&i| self.scan_results[i].path == path)
+            {
+                self.scanner_index = pos;
+            }
+        } else if selected.is_dir {
+            let path = selected.path.clone();
+            self.open_scan_path(path);
         }
     }
 
+    fn cycle_scanner_mode(&mut self, forward: bool) {
+        if self.active_tab != ActiveTab::DeepScanner {
+            return;
+        }
+        self.scanner_mode = if forward {
+            self.scanner_mode.next()
+        } else {
+            self.scanner_mode.prev()
+        };
+        self.scanner_index = 0;
+        self.search_query.clear();
+        self.scan_results.clear();
+        self.scanner_message = None;
+        self.refresh_scan_results();
+    }
+
     fn drill_up(&mut self) {
         if let Some(parent) = self.current_scan_path.parent() {
-            self.current_scan_path = parent.to_path_buf();
-            self.scan_results = scanner::scan_directory(&self.current_scan_path);
-            self.scanner_index = 0;
+            let path = parent.to_path_buf();
+            self.open_scan_path(path);
+        }
+    }
+
+    // Shows a folder straight from the scan tree, only walking the disk if it
+    // lies outside of what has been scanned so far
+    fn open_scan_path(&mut self, path: std::path::PathBuf) {
+        let in_tree = self
+            .scan_tree
+            .as_ref()
+            .is_some_and(|tree| tree.contains_dir(&path));
+        if self.imported_from.is_some() && !in_tree {
+            return;
+        }
+        self.scanner_message = None;
+        self.current_scan_path = path;
+        self.scanner_index = 0;
+        self.search_query.clear();
+
+        if self
+            .scan_tree
+            .as_ref()
+            .is_some_and(|tree| tree.contains_dir(&self.current_scan_path))
+        {
+            self.scan_job = None;
+            self.refresh_scan_results();
+        } else {
+            self.start_scan();
+        }
+    }
+
+    fn refresh_scan_results(&mut self) {
+        // These views look at the files themselves, which an imported scan doesn't have
+        if self.imported_from.is_some()
+            && matches!(
+                self.scanner_mode,
+                ScannerMode::Duplicates | ScannerMode::Empty | ScannerMode::Changes
+            )
+        {
+            self.scan_results.clear();
+            self.row_group.clear();
+            self.scan_changes.clear();
+            self.update_scan_view();
+            return;
+        }
+        match self.scanner_mode {
+            ScannerMode::Duplicates => return self.refresh_duplicates(),
+            ScannerMode::Stale => return self.refresh_stale(),
+            ScannerMode::Types => return self.refresh_types(),
+            ScannerMode::Treemap => return self.refresh_treemap(),
+            ScannerMode::Changes => return self.refresh_changes(),
+            _ => {}
+        }
+        if let Some(tree) = &self.scan_tree {
+            let entries = match self.scanner_mode {
+                ScannerMode::LargestFiles => tree.largest_files(
+                    &self.current_scan_path,
+                    LARGEST_FILES_LIMIT,
+                    self.size_mode,
+                ),
+                ScannerMode::Empty => tree.empty_entries(&self.current_scan_path),
+                _ => tree.entries(&self.current_scan_path).unwrap_or_default(),
+            };
+            self.set_scan_results(entries);
+        }
+    }
+
+    // Lists the duplicate groups for the current folder, kicking off a search
+    // over the scan tree first if they haven't been looked for yet
+    fn refresh_duplicates(&mut self) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        if self.duplicate_root.as_ref() != Some(&self.current_scan_path) {
+            if self
+                .duplicate_job
+                .as_ref()
+                .is_none_or(|job| job.path != self.current_scan_path)
+            {
+                let candidates = tree.same_size_files(&self.current_scan_path);
+                self.duplicate_job = Some(duplicates::spawn_duplicate_search(
+                    &self.current_scan_path,
+                    candidates,
+                ));
+                self.duplicate_progress = duplicates::DuplicateProgress::default();
+                self.duplicates_cancelled = false;
+                self.dedupe_message = None;
+            }
+            self.scan_results.clear();
+            self.row_group.clear();
+            self.update_scan_view();
+            return;
+        }
+
+        duplicates::rank_groups(&mut self.duplicate_groups, self.size_mode);
+        let mut entries = Vec::new();
+        self.row_group.clear();
+        for (i, group) in self.duplicate_groups.iter().enumerate() {
+            for file in &group.files {
+                entries.push(file.clone());
+                self.row_group.push(i);
+            }
+        }
+        self.scan_results = entries;
+        self.update_scan_view();
+    }
+
+    // Groups everything unused for stale_months by the top-level folder it's in,
+    // biggest folders and biggest entries first
+    fn refresh_stale(&mut self) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        let cutoff = chrono::Local::now()
+            .checked_sub_months(chrono::Months::new(self.stale_months))
+            .map(|d| d.timestamp())
+            .unwrap_or(0);
+        let mut groups = tree.stale_entries(&self.current_scan_path, cutoff);
+        groups.sort_by_key(|g| std::cmp::Reverse(g.size_in(self.size_mode)));
+
+        let mut entries = Vec::new();
+        self.row_group.clear();
+        for (i, group) in groups.iter_mut().enumerate() {
+            scanner::sort_entries(&mut group.entries, scanner::SortKey::Size, true, self.size_mode);
+            for entry in &group.entries {
+                entries.push(entry.clone());
+                self.row_group.push(i);
+            }
+        }
+        self.stale_groups = groups;
+        self.scan_results = entries;
+        self.update_scan_view();
+    }
+
+    // The current folder biggest first, each entry coloured by the kind of
+    // file taking up most of its space
+    fn refresh_treemap(&mut self) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        let mut entries = tree.entries(&self.current_scan_path).unwrap_or_default();
+        scanner::sort_entries(&mut entries, scanner::SortKey::Size, true, self.size_mode);
+        self.treemap_colors = entries
+            .iter()
+            .map(|e| (e.path.clone(), tree.main_category(&e.path, self.size_mode)))
+            .collect();
+        self.scan_results = entries;
+        self.update_scan_view();
+    }
+
+    fn treemap_rects(&self) -> Vec<ratatui::layout::Rect> {
+        let sizes: Vec<u64> = self
+            .scan_view
+            .iter()
+            .map(|&i| self.scan_results[i].size_in(self.size_mode))
+            .collect();
+        treemap::layout(&sizes, self.treemap_area.get())
+    }
+
+    // hjkl and the arrows move between rectangles, Enter opens a folder (or
+    // trashes the selection, if there is one) and Backspace goes back up.
+    // Returns false for keys the treemap leaves alone.
+    fn treemap_key(&mut self, key: KeyCode) -> bool {
+        let direction = match key {
+            KeyCode::Left | KeyCode::Char('h') => treemap::Direction::Left,
+            KeyCode::Right | KeyCode::Char('l') => treemap::Direction::Right,
+            KeyCode::Up | KeyCode::Char('k') => treemap::Direction::Up,
+            KeyCode::Down | KeyCode::Char('j') => treemap::Direction::Down,
+            KeyCode::Enter if self.selected_paths.is_empty() => {
+                self.drill_down();
+                return true;
+            }
+            KeyCode::Backspace => {
+                self.drill_up();
+                return true;
+            }
+            _ => return false,
+        };
+        if let Some(next) = treemap::neighbour(&self.treemap_rects(), self.scanner_index, direction) {
+            self.scanner_index = next;
+        }
+        true
+    }
+
+    // One row per extension, plus the category totals for the side panel
+    fn refresh_types(&mut self) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+
+        let mut categories: std::collections::HashMap<filetypes::Category, filetypes::TypeTotals> =
+            std::collections::HashMap::new();
+        let entries = tree
+            .type_breakdown(&self.current_scan_path)
+            .into_iter()
+            .map(|(extension, totals)| {
+                categories
+                    .entry(filetypes::Category::of(&extension))
+                    .or_default()
+                    .add(&totals);
+                scanner::DirEntry {
+                    path: std::path::PathBuf::new(),
+                    name: extension,
+                    size: totals.size,
+                    disk_size: totals.disk_size,
+                    is_dir: false,
+                    shared: false,
+                    is_mount: false,
+                    file_count: totals.files,
+                    mtime: 0,
+                    atime: 0,
+                }
+            })
+            .collect();
+
+        self.type_categories = categories.into_iter().collect();
+        self.type_categories
+            .sort_by_key(|(_, totals)| std::cmp::Reverse(totals.size_in(self.size_mode)));
+        self.set_scan_results(entries);
+    }
+
+    // Folders below the current one that grew or shrank since the indexed scan
+    fn refresh_changes(&mut self) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        self.scan_changes = match &self.scan_index {
+            Some(previous) if previous.contains_key(&self.current_scan_path) => index::changes(
+                previous,
+                &tree.directories_under(&self.current_scan_path),
+                &self.current_scan_path,
+                self.size_mode,
+            ),
+            _ => Vec::new(),
+        };
+        self.scan_results = self.scan_changes.iter().map(|c| c.entry.clone()).collect();
+        self.update_scan_view();
+    }
+
+    // Records every folder of a finished scan in the index. What the index said
+    // before comes back through index_rx for merge_scan_index.
+    fn save_scan_index(&mut self, root: &std::path::Path) {
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        let folders = tree.directories_under(root);
+        let root = root.to_path_buf();
+        let scanned = chrono::Local::now().timestamp();
+
+        let tx = self.index_tx.clone();
+        std::thread::spawn(move || {
+            let previous = index::update(&root, &folders, scanned).unwrap_or_default();
+            let _ = tx.send((root, previous));
+        });
+    }
+
+    // Changes compares every folder against the scan before the latest one, so
+    // a rescanned folder takes its entries from the index as it was just before
+    fn merge_scan_index(&mut self, root: &std::path::Path, previous: index::ScanIndex) {
+        match &mut self.scan_index {
+            Some(scan_index) => {
+                scan_index.retain(|path, _| !path.starts_with(root));
+                scan_index.extend(previous.into_iter().filter(|(path, _)| path.starts_with(root)));
+            }
+            None => self.scan_index = Some(previous),
+        }
+        if self.scanner_mode == ScannerMode::Changes {
+            self.refresh_scan_results();
+        }
+    }
+
+    fn adjust_stale_months(&mut self, delta: i32) {
+        if self.active_tab != ActiveTab::DeepScanner || self.scanner_mode != ScannerMode::Stale {
+            return;
+        }
+        self.stale_months = self.stale_months.saturating_add_signed(delta).clamp(1, 120);
+        self.refresh_scan_results();
+    }
+
+    // "Keep one, trash the rest": selects every other copy of the highlighted
+    // file and makes sure the highlighted one stays unselected
+    fn keep_duplicate(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner
+            || self.scanner_mode != ScannerMode::Duplicates
+            || self.selected_scan_entry().is_none()
+        {
+            return;
+        }
+        let idx = self.scan_view[self.scanner_index];
+        let keep = &self.scan_results[idx].path;
+        let group = &self.duplicate_groups[self.row_group[idx]];
+        for file in &group.files {
+            if &file.path != keep {
+                self.selected_paths.insert(file.path.clone());
+            }
+        }
+        self.selected_paths.remove(keep);
+    }
+
+    // Keeps the highlighted copy and turns every other copy into a link to it
+    fn link_duplicates(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner
+            || self.scanner_mode != ScannerMode::Duplicates
+            || self.selected_scan_entry().is_none()
+            || self.link_job.is_some()
+        {
+            return;
+        }
+        let idx = self.scan_view[self.scanner_index];
+        let keep = self.scan_results[idx].path.clone();
+        let group = self.row_group[idx];
+        let others: Vec<_> = self.duplicate_groups[group]
+            .files
+            .iter()
+            .filter(|f| f.path != keep)
+            .map(|f| f.path.clone())
+            .collect();
+
+        self.link_tally = LinkTally::default();
+        self.dedupe_message = Some("Comparing the copies before linking them...".to_string());
+        self.link_job = Some(duplicates::spawn_dedupe(keep, others));
+    }
+
+    fn poll_links(&mut self) {
+        let Some(job) = &self.link_job else {
+            return;
+        };
+
+        let updates: Vec<_> = job.rx.try_iter().collect();
+        let mut done = None;
+        for update in updates {
+            match update {
+                duplicates::LinkUpdate::Progress(progress) => {
+                    self.dedupe_message = Some(format!(
+                        "Comparing the copies before linking them: {} of {}, [Esc] cancels",
+                        system::format_bytes(progress.bytes_done),
+                        system::format_bytes(progress.bytes_total)
+                    ));
+                }
+                duplicates::LinkUpdate::Linked(path, Ok(record)) => {
+                    if record.kind == duplicates::LinkKind::HardLink {
+                        self.link_tally.hard_linked += 1;
+                        if let Some(tree) = &mut self.scan_tree {
+                            tree.mark_hard_linked(&path, &record.kept);
+                        }
+                    }
+                    for group in &mut self.duplicate_groups {
+                        if let Some(file) = group.files.iter().find(|f| f.path == path) {
+                            self.link_tally.freed += file.disk_size;
+                        }
+                        group.files.retain(|f| f.path != path);
+                    }
+                    self.selected_paths.remove(&path);
+                    self.deduped_items.push(record);
+                    self.link_tally.linked += 1;
+                }
+                duplicates::LinkUpdate::Linked(path, Err(e)) => {
+                    self.link_tally.failed = Some(format!("{}: {}", path.display(), e));
+                }
+                duplicates::LinkUpdate::Finished => done = Some(self.link_tally.message()),
+                duplicates::LinkUpdate::Cancelled => done = Some(format!("{}, then cancelled", self.link_tally.message())),
+                duplicates::LinkUpdate::Unlinked(..) => {}
+            }
+        }
+
+        if let Some(message) = done {
+            self.link_job = None;
+            self.dedupe_message = Some(message);
+            self.duplicate_groups.retain(|g| g.files.len() > 1);
+            self.refresh_scan_results();
+            self.disks = system::get_disks();
+        }
+    }
+
+    fn poll_unlink(&mut self) {
+        let Some(job) = &self.unlink_job else {
+            return;
+        };
+
+        let updates: Vec<_> = job.rx.try_iter().collect();
+        for update in updates {
+            match update {
+                duplicates::LinkUpdate::Progress(progress) => {
+                    self.trash_message = Some(format!(
+                        "Copying the data back into a file of its own: {}%, [Esc] cancels",
+                        progress.bytes_done * 100 / progress.bytes_total.max(1)
+                    ));
+                }
+                duplicates::LinkUpdate::Unlinked(path, Ok(())) => {
+                    self.deduped_items.retain(|item| item.path != path);
+                    self.session_trash_index = self
+                        .session_trash_index
+                        .min(self.session_trash_len().saturating_sub(1));
+                    self.trash_message = None;
+                    self.disks = system::get_disks();
+                }
+                duplicates::LinkUpdate::Unlinked(_, Err(e)) => {
+                    self.trash_message = Some(format!("Couldn't undo: {}", e));
+                }
+                duplicates::LinkUpdate::Cancelled => {
+                    self.trash_message = Some("Undo cancelled, the link is still in place".to_string());
+                    self.unlink_job = None;
+                }
+                duplicates::LinkUpdate::Finished => self.unlink_job = None,
+                duplicates::LinkUpdate::Linked(..) => {}
+            }
+        }
+    }
+
+    fn cancel_links(&mut self) {
+        if let Some(job) = &self.link_job {
+            job.cancel();
+        }
+        if let Some(job) = &self.unlink_job {
+            job.cancel();
+        }
+    }
+
+    fn poll_duplicates(&mut self) {
+        let Some(job) = &self.duplicate_job else {
+            return;
+        };
+
+        let mut finished = None;
+        while let Ok(update) = job.rx.try_recv() {
+            match update {
+                duplicates::DuplicateUpdate::Progress(progress) => self.duplicate_progress = progress,
+                duplicates::DuplicateUpdate::Finished(groups) => finished = Some((groups, false)),
+                duplicates::DuplicateUpdate::Cancelled => finished = Some((Vec::new(), true)),
+            }
+        }
+
+        if let Some((groups, cancelled)) = finished {
+            self.duplicate_root = Some(job.path.clone());
+            self.duplicate_job = None;
+            self.duplicate_groups = groups;
+            self.duplicates_cancelled = cancelled;
+            if self.scanner_mode == ScannerMode::Duplicates {
+                self.refresh_scan_results();
+            }
+        }
+    }
+
+    fn set_scan_results(&mut self, mut entries: Vec<scanner::DirEntry>) {
+        scanner::sort_entries(
+            &mut entries,
+            self.sort_key,
+            self.sort_descending,
+            self.size_mode,
+        );
+        self.scan_results = entries;
+        self.update_scan_view();
+    }
+
+    // Re-sorts in place, keeping the highlight on the same entry
+    fn resort_scan_results(&mut self) {
+        if self.scanner_mode.keeps_own_order() {
+            return;
+        }
+        let selected = self.selected_scan_entry().map(|e| e.path.clone());
+        let entries = std::mem::take(&mut self.scan_results);
+        self.set_scan_results(entries);
+
+        if let Some(path) = selected
+            && let Some(pos) = self
+                .scan_view
+                .iter()
+                .position(|&i| self.scan_results[i].path == path)
+            && (!self.collapse_small || pos < COLLAPSED_ROWS)
+        {
+            self.scanner_index = pos;
+        }
+    }
+
+    fn cycle_sort_key(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner || self.scanner_mode.keeps_own_order() {
+            return;
+        }
+        self.sort_key = self.sort_key.next();
+        self.sort_descending = self.sort_key.descending_by_default();
+        self.resort_scan_results();
+    }
+
+    fn reverse_sort(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner || self.scanner_mode.keeps_own_order() {
+            return;
+        }
+        self.sort_descending = !self.sort_descending;
+        self.resort_scan_results();
+    }
+
+    fn start_scan(&mut self) {
+        if self.imported_from.is_some() {
+            return;
+        }
+        // Replacing the job drops the old one, which cancels its worker
+        let options = scanner::ScanOptions {
+            one_filesystem: self.stays_on_one_filesystem(),
+            exclude: std::sync::Arc::new(self.config.exclude.clone()),
+        };
+        self.scan_job = Some(scanner::spawn_scan(&self.current_scan_path, options));
+        // Duplicates are looked for again once the fresh tree is in
+        self.duplicate_job = None;
+        self.duplicate_root = None;
+        self.scan_results.clear();
+        self.update_scan_view();
+        self.scan_progress = scanner::ScanProgress::default();
+        self.scan_cancelled = false;
+        self.scanner_index = 0;
+    }
+
+    fn toggle_size_mode(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner {
+            return;
+        }
+        self.size_mode = self.size_mode.toggle();
+        if self.scanner_mode != ScannerMode::Folders && self.scan_job.is_none() {
+            // Which files make the cut, and how groups rank, depends on the size mode
+            self.refresh_scan_results();
+        } else {
+            self.resort_scan_results();
+        }
+    }
+
+    fn stays_on_one_filesystem(&self) -> bool {
+        self.one_filesystem
+            .unwrap_or(self.current_scan_path == std::path::Path::new("/"))
+    }
+
+    fn toggle_one_filesystem(&mut self) {
+        if self.active_tab != ActiveTab::DeepScanner || self.imported_from.is_some() {
+            return;
+        }
+        self.one_filesystem = Some(!self.stays_on_one_filesystem());
+        // The tree was built with the old setting, start over from here
+        self.scan_tree = None;
+        self.start_scan();
+    }
+
+    // Browses an ncdu export instead of the local disk. Nothing in it gets
+    // rescanned, trashed or read, its paths belong to another machine.
+    fn open_import(
+        &mut self,
+        file: std::path::PathBuf,
+        tree: scanner::ScanTree,
+        progress: scanner::ScanProgress,
+    ) {
+        self.scan_job = None;
+        self.current_scan_path = tree.root().to_path_buf();
+        self.scan_tree = Some(tree);
+        self.scan_progress = progress;
+        self.imported_from = Some(file);
+        self.active_tab = ActiveTab::DeepScanner;
+        self.refresh_scan_results();
+    }
+
+    fn start_export(&mut self) {
+        if self.active_tab == ActiveTab::DeepScanner
+            && self.scan_job.is_none()
+            && self.scan_tree.is_some()
+        {
+            self.export_prompt = true;
+        }
+    }
+
+    // Writes the current folder's part of the tree in the format picked by `key`
+    fn choose_export(&mut self, key: KeyCode) {
+        self.export_prompt = false;
+        let format = match key {
+            KeyCode::Char('j') => export::ExportFormat::Json,
+            KeyCode::Char('c') => export::ExportFormat::Csv,
+            KeyCode::Char('n') => export::ExportFormat::Ncdu,
+            _ => return,
+        };
+        let Some(tree) = &self.scan_tree else {
+            return;
+        };
+        let out = export::export_path(&self.current_scan_path, format);
+        self.scanner_message = Some(
+            match export::export(tree, &self.current_scan_path, format, &out) {
+                Ok(count) => format!(
+                    "Exported {} entries as {} to {}",
+                    count,
+                    format.label(),
+                    out.display()
+                ),
+                Err(e) => format!("Export failed: {}", e),
+            },
+        );
+    }
+
+    fn rescan(&mut self) {
+        if self.active_tab == ActiveTab::DeepScanner {
+            self.start_scan();
+        }
+    }
+
+    fn cancel_scan(&mut self) {
+        if let Some(job) = &self.scan_job {
+            job.cancel();
+        }
+        if let Some(job) = &self.duplicate_job {
+            job.cancel();
+        }
+    }
+
+    fn poll_scan(&mut self) {
+        let Some(job) = &self.scan_job else {
+            return;
+        };
+
+        let mut partial = None;
+        let mut finished = None;
+        while let Ok(update) = job.rx.try_recv() {
+            match update {
+                scanner::ScanUpdate::Partial(progress, entries) => {
+                    self.scan_progress = progress;
+                    partial = Some(entries);
+                }
+                scanner::ScanUpdate::Finished(progress, tree) => {
+                    self.scan_progress = progress;
+                    finished = Some((tree, false));
+                }
+                scanner::ScanUpdate::Cancelled(progress, tree) => {
+                    self.scan_progress = progress;
+                    finished = Some((tree, true));
+                }
+            }
+        }
+
+        // Partial results only cover the folder listing, other modes wait for the full tree
+        if let Some(entries) = partial
+            && self.scanner_mode == ScannerMode::Folders
+        {
+            self.set_scan_results(entries);
+        }
+
+        if let Some((tree, cancelled)) = finished {
+            self.scan_job = None;
+            self.scan_cancelled = cancelled;
+            self.current_scan_path = tree.root().to_path_buf();
+            self.duplicate_root = None;
+
+            let root = tree.root().to_path_buf();
+            match &mut self.scan_tree {
+                // A refresh of a folder we already know about: swap in the new subtree,
+                // unless it was cancelled halfway and would leave the totals short
+                Some(existing) if existing.contains_dir(tree.root()) => {
+                    if !cancelled {
+                        existing.graft(tree);
+                    }
+                }
+                _ => self.scan_tree = Some(tree),
+            }
+            if !cancelled {
+                self.save_scan_index(&root);
+            }
+            self.refresh_scan_results();
+        }
+
+        if self.scanner_index >= self.scanner_len() {
+            self.scanner_index = self.scanner_len().saturating_sub(1);
         }
     }
 
@@ -306,7 +1505,11 @@ impl App {
             return;
         }
         if self.active_tab == ActiveTab::SessionTrash {
-            self.execute_session_trash_delete();
+            if self.browse_trash {
+                self.delete_trash_entry();
+            } else {
+                self.execute_session_trash_delete();
+            }
             return;
         }
         if self.active_tab == ActiveTab::Snapshots {
@@ -331,7 +1534,7 @@ impl App {
                 self.clean_journal = false;
             }
         if self.clean_trash
-            && system::empty_trash() {
+            && system::empty_trash(&self.config.trash_retention) {
                 self.trash_size = system::get_trash_size();
                 self.clean_trash = false;
             }
@@ -359,67 +1562,323 @@ impl App {
                 self.npm_size = system::get_npm_cache_size();
                 self.clean_npm = false;
             }
+        if !self.selected_artifacts.is_empty() && self.artifacts_clean_rx.is_none() {
+            let selected = &self.selected_artifacts;
+            let artifacts = self
+                .build_artifacts
+                .iter()
+                .filter(|a| {
+                    selected.contains(&a.paths[0]) || a.parts.iter().any(|p| selected.contains(&p.paths[0]))
+                })
+                .cloned()
+                .collect();
+            self.artifacts_clean_rx = Some(system::spawn_artifact_clean(artifacts, selected.clone()));
+        }
+
+        self.disks = system::get_disks();
+    }
+
+    // Failed ones stay listed and selected
+    fn poll_artifact_clean(&mut self) {
+        let Some(rx) = &self.artifacts_clean_rx else {
+            return;
+        };
+
+        let updates: Vec<_> = rx.try_iter().collect();
+        for update in updates {
+            match update {
+                system::ArtifactUpdate::Removed(key) => {
+                    if let Some(artifact) = self.build_artifacts.iter().find(|a| a.paths[0] == key) {
+                        for part in &artifact.parts {
+                            self.selected_artifacts.remove(&part.paths[0]);
+                        }
+                    }
+                    self.selected_artifacts.remove(&key);
+                    self.build_artifacts.retain(|a| a.paths[0] != key);
+                }
+                system::ArtifactUpdate::Trimmed(key, cleaned, parts, size) => {
+                    for part in &cleaned {
+                        self.selected_artifacts.remove(part);
+                    }
+                    if let Some(artifact) = self.build_artifacts.iter_mut().find(|a| a.paths[0] == key) {
+                        artifact.parts = parts;
+                        artifact.size = size;
+                    }
+                }
+                system::ArtifactUpdate::Finished => {
+                    self.artifacts_clean_rx = None;
+                    self.disks = system::get_disks();
+                }
+            }
+        }
+        self.dev_index = self.dev_index.min(self.dev_len() - 1);
+    }
+
+    fn execute_deep_scanner_trash(&mut self) {
+        if self.selected_paths.is_empty() || self.trash_rx.is_some() {
+            return;
+        }
+        if let Some(file) = &self.imported_from {
+            self.scanner_message = Some(format!(
+                "Nothing to trash, these paths come from {}",
+                file.display()
+            ));
+            return;
+        }
+
+        let has_root_files = self.selected_paths.iter().any(|p| !trash::can_trash(p));
+
+        if has_root_files && !self.show_root_warning {
+            self.show_root_warning = true;
+            return;
+        }
+
+        // Proceed with trashing/deleting
+        let paths: Vec<_> = self.selected_paths.drain().collect();
+        self.trash_rx = Some(system::spawn_trash(paths));
+        self.scanner_message = None;
+        self.show_root_warning = false;
+    }
+
+    fn poll_trash(&mut self) {
+        let Some(rx) = &self.trash_rx else {
+            return;
+        };
+
+        let mut finished = false;
+        let mut restored = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            match update {
+                system::TrashUpdate::Copying(path, progress) => self.trash_progress = Some((path, progress)),
+                system::TrashUpdate::Trashed(path, Ok(item)) => {
+                    if let Some(tree) = &mut self.scan_tree {
+                        tree.remove(&path);
+                    }
+                    for group in &mut self.duplicate_groups {
+                        group.files.retain(|f| !f.path.starts_with(&path));
+                    }
+                    self.trashed_items.push(item);
+                    self.trash_progress = None;
+                }
+                system::TrashUpdate::Trashed(path, Err(e)) => {
+                    self.scanner_message = Some(format!("Couldn't trash {}: {}", path.display(), e));
+                    self.trash_progress = None;
+                }
+                system::TrashUpdate::CopyingBack(path, progress) => {
+                    self.trash_message = Some(format!(
+                        "Copying {} ({}) back and checking the copy: {}%",
+                        path.file_name().unwrap_or_default().to_string_lossy(),
+                        system::format_bytes(progress.bytes_total / 2),
+                        progress.bytes_done * 100 / progress.bytes_total.max(1)
+                    ));
+                }
+                system::TrashUpdate::Restored(info_path, Ok(path)) => {
+                    self.trash_message = Some(format!("Restored {}", path.display()));
+                    restored.push(info_path);
+                }
+                // Stays listed if it can't go back, e.g. because something new took its place
+                system::TrashUpdate::Restored(_, Err(e)) => {
+                    self.trash_message = Some(format!("Couldn't restore: {}", e));
+                }
+                system::TrashUpdate::Finished => finished = true,
+            }
+        }
+        for info_path in restored {
+            self.forget_trash_entry(&info_path);
+        }
+
+        if finished {
+            self.trash_rx = None;
+            if self.browse_trash {
+                self.load_trash_entries();
+            }
+            self.duplicate_groups.retain(|g| g.files.len() > 1);
+            self.refresh_scan_results();
+            self.disks = system::get_disks();
+        }
+    }
+
+    fn session_trash_len(&self) -> usize {
+        self.trashed_items.len() + self.deduped_items.len()
+    }
+
+    fn execute_session_trash_delete(&mut self) {
+        if self.session_trash_len() == 0 {
+            return;
+        }
+        if self.session_trash_index < self.trashed_items.len() {
+            let item = self.trashed_items.remove(self.session_trash_index);
+            let _ = system::perm_delete_trash_item(&item);
+        } else {
+            // Not while it's being undone
+            if self.unlink_job.is_some() {
+                return;
+            }
+            // Nothing to delete for a link, confirming just drops it from the list
+            let item = self
+                .deduped_items
+                .remove(self.session_trash_index - self.trashed_items.len());
+            duplicates::keep_link(&item);
+        }
 
+        if self.session_trash_index >= self.session_trash_len() && self.session_trash_index > 0 {
+            self.session_trash_index -= 1;
+        }
         self.disks = system::get_disks();
     }
 
-    fn execute_deep_scanner_trash(&mut self) {
-        if self.selected_paths.is_empty() {
+    fn execute_undo_trash(&mut self) {
+        if self.active_tab != ActiveTab::SessionTrash {
             return;
         }
-
-        let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("~"));
-        let mut has_root_files = false;
-        for path in &self.selected_paths {
-            if !path.starts_with(&home_dir) {
-                has_root_files = true;
-                break;
+        if self.browse_trash {
+            self.restore_trash_entry();
+            return;
+        }
+        if self.session_trash_len() == 0 {
+            return;
+        }
+        if self.session_trash_index < self.trashed_items.len() {
+            // Dropped from the list once poll_trash hears it went back
+            if self.trash_rx.is_none() {
+                match system::restore_trash_item(&self.trashed_items[self.session_trash_index]) {
+                    Ok(rx) => self.trash_rx = Some(rx),
+                    Err(e) => self.trash_message = Some(format!("Couldn't restore: {}", e)),
+                }
             }
+            return;
+        }
+        // Dropped from the list once poll_unlink hears it has its own copy again
+        if self.unlink_job.is_none() {
+            let index = self.session_trash_index - self.trashed_items.len();
+            self.unlink_job = Some(duplicates::spawn_undo(self.deduped_items[index].clone()));
         }
+    }
 
-        if has_root_files && !self.show_root_warning {
-            self.show_root_warning = true;
+    fn toggle_trash_view(&mut self) {
+        if self.active_tab != ActiveTab::SessionTrash {
             return;
         }
+        self.browse_trash = !self.browse_trash;
+        self.trash_message = None;
+        if self.browse_trash {
+            self.load_trash_entries();
+        }
+    }
 
-        // Proceed with trashing/deleting
-        let paths: Vec<_> = self.selected_paths.drain().collect();
-        for path in paths {
-            if let Ok(item) = system::move_to_trash(&path) {
-                self.trashed_items.push(item);
-            }
+    // Sizing whole folders in the trash can take a moment, so it happens on a worker thread
+    fn load_trash_entries(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.trash_entries_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(trash::list_entries());
+        });
+    }
+
+    fn update_trash_view(&mut self) {
+        let now = chrono::Local::now().naive_local();
+        let filter = self.trash_filter.to_lowercase();
+        self.trash_view = self
+            .trash_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| self.trash_age.includes(e.deletion_date, now))
+            .filter(|(_, e)| {
+                filter.is_empty()
+                    || e.original_path
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .contains(&filter)
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if self.trash_browser_index >= self.trash_view.len() {
+            self.trash_browser_index = self.trash_view.len().saturating_sub(1);
         }
+    }
 
-        self.show_root_warning = false;
-        self.scan_results = scanner::scan_directory(&self.current_scan_path);
-        self.scanner_index = 0;
-        self.disks = system::get_disks();
+    fn start_trash_filter(&mut self) {
+        if self.active_tab == ActiveTab::SessionTrash && self.browse_trash {
+            self.trash_filter_input = true;
+        }
     }
 
-    fn execute_session_trash_delete(&mut self) {
-        if self.trashed_items.is_empty() {
-            return;
+    fn edit_trash_filter(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc => {
+                self.trash_filter_input = false;
+                self.trash_filter.clear();
+            }
+            KeyCode::Enter => {
+                self.trash_filter_input = false;
+                return;
+            }
+            KeyCode::Down => return self.next_item(),
+            KeyCode::Up => return self.prev_item(),
+            KeyCode::Backspace => {
+                self.trash_filter.pop();
+            }
+            KeyCode::Char(c) => self.trash_filter.push(c),
+            _ => return,
         }
-        let item = self.trashed_items.remove(self.session_trash_index);
-        let _ = system::perm_delete_trash_item(&item);
+        self.trash_browser_index = 0;
+        self.update_trash_view();
+    }
 
-        if self.session_trash_index >= self.trashed_items.len() && self.session_trash_index > 0 {
-            self.session_trash_index -= 1;
+    fn cycle_trash_age(&mut self) {
+        if self.active_tab == ActiveTab::SessionTrash && self.browse_trash {
+            self.trash_age = self.trash_age.next();
+            self.trash_browser_index = 0;
+            self.update_trash_view();
         }
+    }
+
+    fn selected_trash_entry(&self) -> Option<&trash::TrashEntry> {
+        self.trash_view
+            .get(self.trash_browser_index)
+            .map(|&i| &self.trash_entries[i])
+    }
+
+    // Drops an entry that was restored or deleted from both lists
+    fn forget_trash_entry(&mut self, info_path: &std::path::Path) {
+        self.trash_entries.retain(|e| e.info_path() != info_path);
+        self.trashed_items.retain(|item| item.trash_info_path != info_path);
+        self.session_trash_index = self
+            .session_trash_index
+            .min(self.session_trash_len().saturating_sub(1));
+        self.update_trash_view();
+        self.trash_size = system::get_trash_size();
         self.disks = system::get_disks();
     }
 
-    fn execute_undo_trash(&mut self) {
-        if self.active_tab != ActiveTab::SessionTrash || self.trashed_items.is_empty() {
+    fn restore_trash_entry(&mut self) {
+        if self.trash_rx.is_some() {
             return;
         }
-        let item = self.trashed_items.remove(self.session_trash_index);
-        let _ = system::restore_trash_item(&item);
+        let Some(entry) = self.selected_trash_entry() else {
+            return;
+        };
+        // Entries from another filesystem get copied back, which can take a while
+        self.trash_rx = Some(system::spawn_restore(
+            entry.file_path(),
+            entry.info_path(),
+            entry.topdir.clone(),
+        ));
+    }
 
-        if self.session_trash_index >= self.trashed_items.len() && self.session_trash_index > 0 {
-            self.session_trash_index -= 1;
+    fn delete_trash_entry(&mut self) {
+        let Some(entry) = self.selected_trash_entry() else {
+            return;
+        };
+        let info_path = entry.info_path();
+        match trash::delete_entry(entry) {
+            Ok(()) => {
+                self.trash_message = Some(format!("Deleted {} for good", entry.original_path.display()));
+                self.forget_trash_entry(&info_path);
+            }
+            Err(e) => self.trash_message = Some(format!("Couldn't delete: {}", e)),
         }
-        self.disks = system::get_disks();
     }
 
     fn execute_snapshot_delete(&mut self) {
@@ -466,11 +1925,35 @@ impl App {
 }
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let import = match args.as_slice() {
+        [] => None,
+        [flag, rest @ ..] if flag == "--purge-trash" && (rest.is_empty() || rest == ["--dry-run"]) => {
+            std::process::exit(purge_trash(!rest.is_empty()));
+        }
+        [flag, file] if flag == "--import" => {
+            match export::import_ncdu(std::path::Path::new(file)) {
+                Ok((tree, progress)) => Some((std::path::PathBuf::from(file), tree, progress)),
+                Err(e) => {
+                    eprintln!("diskord: can't import {}: {}", file, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("usage: diskord [--import <ncdu export> | --purge-trash [--dry-run]]");
+            std::process::exit(2);
+        }
+    };
+
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
     let mut app = App::new();
+    if let Some((file, tree, progress)) = import {
+        app.open_import(file, tree, progress);
+    }
 
     while !app.should_quit {
         // Poll background snapshot loader each frame
@@ -483,16 +1966,79 @@ fn main() -> Result<()> {
                 app.disks = system::get_disks();
             }
 
+        if let Some(rx) = &app.artifacts_rx
+            && let Ok(artifacts) = rx.try_recv() {
+                app.build_artifacts = artifacts;
+                app.artifacts_rx = None;
+            }
+
+        if let Some(rx) = &app.trash_entries_rx
+            && let Ok(entries) = rx.try_recv() {
+                app.trash_entries = entries;
+                app.trash_entries_rx = None;
+                app.update_trash_view();
+            }
+
+        while let Ok((root, previous)) = app.index_rx.try_recv() {
+            app.merge_scan_index(&root, previous);
+        }
+
+        // Pull in whatever the background scanner has found since the last frame
+        app.poll_scan();
+        app.poll_duplicates();
+        app.poll_links();
+        app.poll_unlink();
+        app.poll_trash();
+        app.poll_artifact_clean();
+
         terminal.draw(|f| ui(f, &app))?;
 
         if event::poll(Duration::from_millis(50))?
             && let Event::Key(key) = event::read()?
                 && key.kind == KeyEventKind::Press {
+                    // While typing a search every key edits the query
+                    if app.search_input {
+                        app.edit_search(key.code);
+                        continue;
+                    }
+                    if app.export_prompt {
+                        app.choose_export(key.code);
+                        continue;
+                    }
+                    if app.trash_filter_input {
+                        app.edit_trash_filter(key.code);
+                        continue;
+                    }
+                    if app.active_tab == ActiveTab::DeepScanner
+                        && app.scanner_mode == ScannerMode::Treemap
+                        && !app.show_root_warning
+                        && app.treemap_key(key.code)
+                    {
+                        continue;
+                    }
+
                     match key.code {
                         KeyCode::Char('q') => app.should_quit = true,
                         KeyCode::Esc => {
                             if app.show_root_warning {
                                 app.show_root_warning = false;
+                            } else if app.link_job.is_some() || app.unlink_job.is_some() {
+                                app.cancel_links();
+                            } else if app.active_tab == ActiveTab::DeepScanner
+                                && (app.scan_job.is_some()
+                                    || (app.duplicate_job.is_some()
+                                        && app.scanner_mode == ScannerMode::Duplicates))
+                            {
+                                app.cancel_scan();
+                            } else if app.active_tab == ActiveTab::DeepScanner
+                                && !app.search_query.is_empty()
+                            {
+                                app.clear_search();
+                            } else if app.active_tab == ActiveTab::SessionTrash
+                                && app.browse_trash
+                                && !app.trash_filter.is_empty()
+                            {
+                                app.edit_trash_filter(KeyCode::Esc);
                             } else {
                                 app.should_quit = true;
                             }
@@ -516,9 +2062,33 @@ fn main() -> Result<()> {
                         KeyCode::Tab => app.next_tab(),
                         KeyCode::BackTab => app.prev_tab(),
                         KeyCode::Char(' ') => app.toggle_selection(),
+                        KeyCode::Char('A') => app.select_all_visible(),
                         KeyCode::Enter => app.execute_clean(),
                         KeyCode::Char('u') => app.execute_undo_trash(),
                         KeyCode::Char('c') => app.execute_snapshot_create(),
+                        KeyCode::Char('r') => app.rescan(),
+                        KeyCode::Char('a') => app.toggle_size_mode(),
+                        KeyCode::Char('x') => app.toggle_one_filesystem(),
+                        KeyCode::Char('o') => app.toggle_collapse_small(),
+                        KeyCode::Char('s') => app.cycle_sort_key(),
+                        KeyCode::Char('S') => app.reverse_sort(),
+                        KeyCode::Char('/') if app.active_tab == ActiveTab::SessionTrash => app.start_trash_filter(),
+                        KeyCode::Char('/') => app.start_search(),
+                        KeyCode::Char('d') => app.cycle_trash_age(),
+                        KeyCode::Char('e') => app.start_export(),
+                        KeyCode::Char('v') | KeyCode::Char('V') if app.active_tab == ActiveTab::SessionTrash => {
+                            app.toggle_trash_view()
+                        }
+                        KeyCode::Char('v') => app.cycle_scanner_mode(true),
+                        KeyCode::Char('V') => app.cycle_scanner_mode(false),
+                        KeyCode::Char('K') => app.keep_duplicate(),
+                        KeyCode::Char('L') => app.link_duplicates(),
+                        KeyCode::Char('+') | KeyCode::Char('=') => app.adjust_stale_months(1),
+                        KeyCode::Char('-') => app.adjust_stale_months(-1),
+                        KeyCode::PageDown => app.scroll_scanner(20),
+                        KeyCode::PageUp => app.scroll_scanner(-20),
+                        KeyCode::Home => app.scroll_scanner(isize::MIN),
+                        KeyCode::End => app.scroll_scanner(isize::MAX),
                         _ => {}
                     }
                 }
@@ -529,6 +2099,39 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+// Applies the [trash] retention rules without starting the interface, for
+// running from a timer. Returns the exit code.
+fn purge_trash(dry_run: bool) -> i32 {
+    let retention = config::DiskordConfig::load().trash_retention;
+    if !retention.is_set() {
+        eprintln!(
+            "diskord: no trash retention rules, set max_age_days or max_size_gb under [trash] in {}",
+            config::DiskordConfig::path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "the config file".to_string())
+        );
+        return 1;
+    }
+
+    let report = trash::apply_retention(&retention, dry_run);
+    let verb = if dry_run { "Would purge" } else { "Purged" };
+    for entry in &report.purged {
+        println!("{} {}", verb, entry.original_path.display());
+    }
+    let freed: u64 = report.purged.iter().map(|e| e.size).sum();
+    println!(
+        "{} {} item(s), {} (items {})",
+        verb,
+        report.purged.len(),
+        system::format_bytes(freed),
+        retention.describe()
+    );
+    for error in &report.errors {
+        eprintln!("diskord: couldn't purge {}", error);
+    }
+    if report.errors.is_empty() { 0 } else { 1 }
+}
+
 fn ui(f: &mut Frame, app: &App) {
     let size = f.area();
 
@@ -629,12 +2232,43 @@ fn ui(f: &mut Frame, app: &App) {
         ActiveTab::DeepScanner => {
             if app.show_root_warning {
                 " [Enter] Confirm PERMANENT DELETE   [Esc] Cancel"
+            } else if app.search_input {
+                " Type to filter   [Up/Down] Navigate   [Enter] Keep Filter   [Esc] Clear Search"
+            } else if app.export_prompt {
+                " Export this folder as:   [j] JSON   [c] CSV   [n] ncdu   [Esc] Cancel"
+            } else if app.scan_job.is_some() {
+                " [Esc] Cancel Scan   [Space] Toggle Select   [h/l] Navigate Folder"
+            } else if app.duplicate_job.is_some() && app.scanner_mode == ScannerMode::Duplicates {
+                " [Esc] Cancel Duplicate Search   [h/l] Navigate Folder   [v] View"
+            } else if app.scanner_mode == ScannerMode::Empty {
+                " [Space] Select  [A] Select All  [Enter] Trash  [h/l] Navigate  [r] Rescan  [s/S] Sort  [/] Search  [v] View"
+            } else if app.scanner_mode == ScannerMode::Types {
+                " [h] Up  [r] Rescan  [a] Size  [s/S] Sort  [/] Search  [v] View"
+            } else if app.scanner_mode == ScannerMode::Treemap {
+                " [hjkl] Move  [Enter] Open Folder, or Trash Selected  [Backspace] Up  [Space] Select  [r] Rescan  [a] Size  [/] Search  [v] View"
+            } else if app.scanner_mode == ScannerMode::Changes {
+                " [Space] Select  [Enter] Trash  [l] Open Folder  [h] Up  [r] Rescan  [a] Size  [/] Search  [v] View"
+            } else if app.scanner_mode == ScannerMode::Stale {
+                " [Space] Select  [Enter] Trash  [+/-] Months Unused  [h/l] Navigate  [r] Rescan  [a] Size  [/] Search  [v] View"
+            } else if app.scanner_mode == ScannerMode::Duplicates {
+                " [Space] Select  [K] Keep This, Select Rest  [L] Keep This, Link Rest  [Enter] Trash  [h/l] Navigate  [r] Rescan  [a] Size  [/] Search  [v] View"
             } else {
-                " [Space] Toggle Select   [Enter] Move Selected to Trash   [h/l] Navigate Folder   [Missing space? Check Snapshots tab]"
+                " [Space] Select  [Enter] Trash  [h/l] Navigate  [r] Rescan  [a] Size  [x] One FS  [o] Collapse  [s/S] Sort  [/] Search  [v] View  [Missing space? Snapshots tab]"
             }
         }
+        ActiveTab::Developer => {
+            " [h/l, Tab] Switch Tabs   [j/k] Navigate   [Space] Select   [A] Select All Artifacts   [Enter] Clean   [q/Esc] Quit"
+        }
         ActiveTab::SessionTrash => {
-            " [u] Undo/Restore   [Enter] Permanently Delete Selected   [h/l, Tab] Switch Tabs   [Missing space? Check Snapshots tab]"
+            if app.trash_filter_input {
+                " Type to filter by original location   [Up/Down] Navigate   [Enter] Keep Filter   [Esc] Clear Filter"
+            } else if app.browse_trash {
+                " [u] Restore   [Enter] Permanently Delete   [/] Filter Location   [d] Filter Age   [v] This Session   [h/l, Tab] Switch Tabs"
+            } else if app.session_trash_index >= app.trashed_items.len() {
+                " [u] Undo Link, Restore Separate Copy   [Enter] Keep Link   [h/l, Tab] Switch Tabs"
+            } else {
+                " [u] Undo/Restore   [Enter] Permanently Delete Selected   [v] Whole Trash   [h/l, Tab] Switch Tabs   [Missing space? Check Snapshots tab]"
+            }
         }
         ActiveTab::Snapshots => {
             if app.snapshots_loading {
@@ -672,7 +2306,15 @@ fn render_system_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
             app.journal_size,
             app.clean_journal,
         ),
-        format_target("User Trash", app.trash_size, app.clean_trash),
+        if app.config.trash_retention.is_set() {
+            format_target(
+                &format!("User Trash (purges items {})", app.config.trash_retention.describe()),
+                app.trash_size,
+                app.clean_trash,
+            )
+        } else {
+            format_target("User Trash", app.trash_size, app.clean_trash)
+        },
         format_target(
             &format!("Orphaned Packages ({})", app.orphaned_count),
             app.orphaned_size,
@@ -708,6 +2350,11 @@ fn render_dev_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
         format_target("NPM Cache (~/.npm/_cacache)", app.npm_size, app.clean_npm),
     ];
 
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)])
+        .split(area);
+
     let mut items = vec![];
     for (i, text) in list_items.into_iter().enumerate() {
         let style = if i == app.dev_index {
@@ -722,7 +2369,108 @@ fn render_dev_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     }
 
     let list = List::new(items);
-    f.render_widget(list, area);
+    f.render_widget(list, chunks[0]);
+
+    render_build_artifacts(f, app, chunks[1]);
+}
+
+fn render_build_artifacts(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let roots: Vec<String> = app
+        .config
+        .project_roots
+        .iter()
+        .map(|p| home_relative(p))
+        .collect();
+    let total: u64 = app.build_artifacts.iter().map(|a| a.size).sum();
+    let title = if app.artifacts_clean_rx.is_some() {
+        format!(" Build artifacts: cleaning the selected folders in {}... ", roots.join(", "))
+    } else if app.artifacts_rx.is_some() {
+        format!(" Build artifacts: looking for projects in {}... ", roots.join(", "))
+    } else {
+        format!(
+            " Build artifacts in {}: {} in {} folder(s) ",
+            roots.join(", "),
+            system::format_bytes(total),
+            app.build_artifacts.len()
+        )
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::TOP)
+        .border_style(Style::default().fg(app.theme.color8));
+
+    let max_name_len = (area.width as usize).saturating_sub(44);
+    let items: Vec<ListItem> = app
+        .artifact_rows()
+        .into_iter()
+        .map(|(artifact, part)| {
+            let artifact = &app.build_artifacts[artifact];
+            let whole_selected = app.selected_artifacts.contains(&artifact.paths[0]);
+
+            if let Some(part) = part {
+                let part = &artifact.parts[part];
+                let checkbox = if whole_selected || app.selected_artifacts.contains(&part.paths[0]) {
+                    "[X]"
+                } else {
+                    "[ ]"
+                };
+                let name = match part.name {
+                    "incremental" => "incremental (all profiles)".to_string(),
+                    "doc" => "doc".to_string(),
+                    profile => format!("{} profile", profile),
+                };
+                return ListItem::new(format!(
+                    "     {} {:<width$} {:>10}",
+                    checkbox,
+                    name,
+                    system::format_bytes(part.size),
+                    width = max_name_len.saturating_sub(4)
+                ))
+                .style(Style::default().fg(app.theme.color8));
+            }
+
+            let checkbox = if whole_selected { "[X]" } else { "[ ]" };
+            let mut name = format!("{}/{}", home_relative(&artifact.project), artifact.name);
+            if artifact.paths.len() > 1 {
+                name.push_str(&format!(" ({} folders)", artifact.paths.len()));
+            }
+            if name.chars().count() > max_name_len && max_name_len > 3 {
+                let skip = name.chars().count() - max_name_len + 3;
+                name = format!("...{}", name.chars().skip(skip).collect::<String>());
+            }
+            ListItem::new(format!(
+                " {} {:<width$} {:>10}   last active {}",
+                checkbox,
+                name,
+                system::format_bytes(artifact.size),
+                format_date(artifact.last_activity),
+                width = max_name_len
+            ))
+        })
+        .collect();
+
+    let mut state = ratatui::widgets::ListState::default();
+    state.select(app.dev_index.checked_sub(3));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(app.theme.foreground))
+        .highlight_style(
+            Style::default()
+                .fg(app.theme.background)
+                .bg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+// Shortens paths inside the home directory to ~/...
+fn home_relative(path: &std::path::Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(|p| p.to_path_buf())) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
 }
 
 fn render_apps_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
@@ -754,62 +2502,547 @@ fn render_apps_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
 
 fn render_deep_scan_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     if app.show_root_warning {
-        let p = Paragraph::new("\n\nWarning: System files selected.\nTrashing outside your Home directory is not supported.\nThese items will be PERMANENTLY DELETED.\n\nPress [Enter] to confirm permanent deletion, or [Esc] to cancel.")
+        let p = Paragraph::new("\n\nWarning: System files selected.\nYou can't move them to a trash without root rights.\nThese items will be PERMANENTLY DELETED.\n\nPress [Enter] to confirm permanent deletion, or [Esc] to cancel.")
             .alignment(Alignment::Center)
             .style(Style::default().fg(ratatui::style::Color::Red));
         f.render_widget(p, area);
         return;
     }
 
-    let mut items = vec![];
+    let (excluded_count, excluded_bytes) = app
+        .scan_tree
+        .as_ref()
+        .map(|tree| tree.excluded_under(&app.current_scan_path, app.size_mode))
+        .unwrap_or((0, 0));
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(if excluded_count > 0 { 1 } else { 0 }),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let order = if app.scanner_mode == ScannerMode::Duplicates {
+        "ranked by reclaimable space".to_string()
+    } else if app.scanner_mode == ScannerMode::Stale {
+        "grouped by top-level folder".to_string()
+    } else if app.scanner_mode == ScannerMode::Changes {
+        "biggest change first".to_string()
+    } else if app.scanner_mode == ScannerMode::Treemap {
+        format!("sized by {}", app.size_mode.label())
+    } else {
+        format!(
+            "sort: {} {}",
+            app.sort_key.label(),
+            if app.sort_descending { "desc" } else { "asc" }
+        )
+    };
+    let mut title = format!(
+        " {}: {}   [{}] ",
+        app.scanner_mode.label(),
+        app.current_scan_path.display(),
+        order
+    );
+    if app.search_input || !app.search_query.is_empty() {
+        let cursor = if app.search_input { "_" } else { "" };
+        title.push_str(&format!(
+            "  Search: {}{}  ({} of {}) ",
+            app.search_query,
+            cursor,
+            app.scan_view.len(),
+            app.scan_results.len()
+        ));
+    }
+    let list_block = Block::default()
+        .title(title)
+        .borders(Borders::BOTTOM)
+        .border_style(Style::default().fg(app.theme.color8));
 
-    for entry in app.scan_results.iter() {
-        let is_selected = app.selected_paths.contains(&entry.path);
-        let checkbox = if is_selected { "[X]" } else { "[ ]" };
-        let prefix = if entry.is_dir { "[DIR]" } else { "[FILE]" };
-        let size_str = system::format_bytes(entry.size);
+    // The file types view gets a per-category panel on the left
+    let list_area = if app.scanner_mode == ScannerMode::Types {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(46), Constraint::Min(0)])
+            .split(chunks[0]);
+        render_type_panel(f, app, columns[0]);
+        columns[1]
+    } else {
+        chunks[0]
+    };
 
-        let max_name_len = (area.width as usize).saturating_sub(30);
-        let mut display_name = entry.name.clone();
-        if display_name.len() > max_name_len && max_name_len > 3 {
-            display_name.truncate(max_name_len - 3);
-            display_name.push_str("...");
+    if app.scanner_mode == ScannerMode::Treemap {
+        render_treemap(f, app, list_area, list_block);
+    } else {
+        // Only build rows for the visible window, folders can hold many thousands of entries
+        let height = (list_block.inner(list_area).height as usize).max(1);
+        let total_rows = app.scanner_len();
+        let mut offset = app.scanner_offset.get();
+        if app.scanner_index < offset {
+            offset = app.scanner_index;
+        } else if app.scanner_index >= offset + height {
+            offset = app.scanner_index + 1 - height;
+        }
+        offset = offset.min(total_rows.saturating_sub(height));
+        app.scanner_offset.set(offset);
+
+        // Sorting by date or item count shows that value in an extra column,
+        // duplicates show how many copies there are, stale entries when they were last
+        // used and file types how many files they cover
+        let detail_width = match app.sort_key {
+            _ if app.scanner_mode.keeps_own_order() || app.scanner_mode == ScannerMode::Types => 14,
+            scanner::SortKey::Modified | scanner::SortKey::Items => 14,
+            _ => 0,
+        };
+        let max_name_len = (list_area.width as usize).saturating_sub(30 + detail_width);
+        let mut items = vec![];
+
+        for row in offset..(offset + height).min(total_rows) {
+            let text = if app.collapse_small && row == COLLAPSED_ROWS {
+                let rest = &app.scan_view[COLLAPSED_ROWS..];
+                let rest_size: u64 = rest
+                    .iter()
+                    .map(|&i| app.scan_results[i].size_in(app.size_mode))
+                    .sum();
+                let label = if app.sort_key == scanner::SortKey::Size && app.sort_descending {
+                    "smaller"
+                } else {
+                    "more"
+                };
+                Line::from(format!(
+                    "     [...] {:<width$} {}",
+                    format!("{} {} items", rest.len(), label),
+                    system::format_bytes(rest_size),
+                    width = max_name_len
+                ))
+            } else if app.scanner_mode == ScannerMode::Types {
+                format_type_row(app, &app.scan_results[app.scan_view[row]], max_name_len)
+            } else {
+                format_scan_entry(app, app.scan_view[row], max_name_len)
+            };
+            items.push(ListItem::new(text).style(Style::default().fg(app.theme.foreground)));
         }
 
-        let text = format!(
-            " {} {} {:<width$} {}",
-            checkbox,
-            prefix,
-            display_name,
-            size_str,
-            width = max_name_len
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(app.scanner_index.saturating_sub(offset)));
+
+        let list = List::new(items)
+            .block(list_block)
+            .highlight_style(
+                Style::default()
+                    .fg(app.theme.background)
+                    .bg(app.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        f.render_stateful_widget(list, list_area, &mut state);
+    }
+
+    let needs_files = matches!(
+        app.scanner_mode,
+        ScannerMode::Duplicates | ScannerMode::Empty | ScannerMode::Changes
+    );
+    let status = if app.imported_from.is_some() && needs_files {
+        " This view needs the files themselves, it isn't available for an imported scan".to_string()
+    } else if app.scan_job.is_none() && app.scanner_mode == ScannerMode::Duplicates {
+        duplicates_status(app)
+    } else if app.scan_job.is_none() && app.scanner_mode == ScannerMode::Stale {
+        stale_status(app)
+    } else if app.scan_job.is_none() && app.scanner_mode == ScannerMode::Changes {
+        changes_status(app)
+    } else if app.scan_job.is_none() && app.scanner_mode == ScannerMode::Empty {
+        let folders = app.scan_results.iter().filter(|e| e.is_dir).count();
+        format!(
+            " {} empty folder(s) and {} zero-byte file(s), [A] selects them all for the trash",
+            folders,
+            app.scan_results.len() - folders
+        )
+    } else if app.scan_job.is_some() {
+        format!(
+            " Scanning... {} files, {} ({}) counted so far",
+            app.scan_progress.files,
+            system::format_bytes(app.scan_progress.bytes_in(app.size_mode)),
+            app.size_mode.label()
+        )
+    } else if app.scan_cancelled {
+        format!(
+            " Scan cancelled, showing partial results ({} files, {} {})",
+            app.scan_progress.files,
+            system::format_bytes(app.scan_progress.bytes_in(app.size_mode)),
+            app.size_mode.label()
+        )
+    } else {
+        let folder_size = app
+            .scan_tree
+            .as_ref()
+            .and_then(|tree| tree.dir_size(&app.current_scan_path, app.size_mode))
+            .unwrap_or(0);
+        let hint = if app.imported_from.is_some() {
+            "in the export"
+        } else {
+            "scanned, press [r] to rescan"
+        };
+        format!(
+            " {} {} in this folder ({} files {})",
+            system::format_bytes(folder_size),
+            app.size_mode.label(),
+            app.scan_progress.files,
+            hint
+        )
+    };
+    let mut status = if let Some(file) = &app.imported_from {
+        format!("{}   [imported from {}]", status, file.display())
+    } else if app.stays_on_one_filesystem() {
+        format!("{}   [one filesystem]", status)
+    } else {
+        status
+    };
+    if let Some((path, progress)) = &app.trash_progress {
+        status = format!(
+            "{}   Copying {} ({}) to the trash and checking the copy: {}%",
+            status,
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            system::format_bytes(progress.bytes_total / 2),
+            progress.bytes_done * 100 / progress.bytes_total.max(1)
         );
-        items.push(ListItem::new(text).style(Style::default().fg(app.theme.foreground)));
+    }
+    if let Some(message) = &app.scanner_message {
+        status = format!("{}   {}", status, message);
+    }
+    if excluded_count > 0 {
+        let excluded_line = Paragraph::new(format!(
+            " Excluded by config: {} in {} path(s), not listed above",
+            system::format_bytes(excluded_bytes),
+            excluded_count
+        ))
+        .style(Style::default().fg(app.theme.color3));
+        f.render_widget(excluded_line, chunks[1]);
     }
 
-    let mut state = ratatui::widgets::ListState::default();
-    state.select(Some(app.scanner_index));
+    let status_line = Paragraph::new(status).style(Style::default().fg(app.theme.color7));
+    f.render_widget(status_line, chunks[2]);
+}
 
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .title(format!(" Path: {} ", app.current_scan_path.display()))
-                .borders(Borders::BOTTOM)
-                .border_style(Style::default().fg(app.theme.color8)),
+fn duplicates_status(app: &App) -> String {
+    if app.duplicate_job.is_some() {
+        let progress = &app.duplicate_progress;
+        format!(
+            " Looking for duplicates... {} {} of {} files ({} read)",
+            if progress.full_pass {
+                "hashing"
+            } else {
+                "comparing the start of"
+            },
+            progress.files_done,
+            progress.files_total,
+            system::format_bytes(progress.bytes_read)
         )
-        .highlight_style(
+    } else if app.duplicates_cancelled {
+        " Duplicate search cancelled, press [r] to rescan and search again".to_string()
+    } else {
+        let reclaimable: u64 = app
+            .duplicate_groups
+            .iter()
+            .map(|g| g.reclaimable(app.size_mode))
+            .sum();
+        let summary = format!(
+            " {} sets of identical files, {} {} reclaimable by keeping one copy of each",
+            app.duplicate_groups.len(),
+            system::format_bytes(reclaimable),
+            app.size_mode.label()
+        );
+        match &app.dedupe_message {
+            Some(message) => format!("{}   {}", summary, message),
+            None => summary,
+        }
+    }
+}
+
+fn changes_status(app: &App) -> String {
+    let Some(previous) = &app.scan_index else {
+        return " Reading the scan index...".to_string();
+    };
+    let Some(before) = previous.get(&app.current_scan_path) else {
+        return " No earlier scan of this folder to compare with, the next session will compare against this one".to_string();
+    };
+    let now = app
+        .scan_tree
+        .as_ref()
+        .and_then(|tree| tree.dir_size(&app.current_scan_path, app.size_mode))
+        .unwrap_or(0);
+    let delta = now as i64 - before.size_in(app.size_mode) as i64;
+    format!(
+        " Since the scan on {}: {} {} ({} -> {}), {} folder(s) changed",
+        format_date(before.scanned),
+        format_delta(delta),
+        app.size_mode.label(),
+        system::format_bytes(before.size_in(app.size_mode)),
+        system::format_bytes(now),
+        app.scan_changes.len()
+    )
+}
+
+fn stale_status(app: &App) -> String {
+    let total: u64 = app
+        .stale_groups
+        .iter()
+        .map(|g| g.size_in(app.size_mode))
+        .sum();
+    let mut status = format!(
+        " {} {} not modified or read in {} months, in {} top-level folder(s)",
+        system::format_bytes(total),
+        app.size_mode.label(),
+        app.stale_months,
+        app.stale_groups.len()
+    );
+
+    // Total for the folder the highlighted entry belongs to
+    if app.selected_scan_entry().is_some()
+        && let Some(group) = app
+            .row_group
+            .get(app.scan_view[app.scanner_index])
+            .and_then(|&g| app.stale_groups.get(g))
+    {
+        status.push_str(&format!(
+            "   {}: {} in {} item(s)",
+            group.name,
+            system::format_bytes(group.size_in(app.size_mode)),
+            group.entries.len()
+        ));
+    }
+    status
+}
+
+fn render_treemap(f: &mut Frame, app: &App, area: ratatui::layout::Rect, block: Block) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    app.treemap_area.set(inner);
+
+    for (row, rect) in app.treemap_rects().into_iter().enumerate() {
+        if rect.area() == 0 {
+            continue;
+        }
+        let entry = &app.scan_results[app.scan_view[row]];
+        let style = if row == app.scanner_index {
             Style::default()
                 .fg(app.theme.background)
                 .bg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
+                .add_modifier(Modifier::BOLD)
+        } else {
+            let category = app
+                .treemap_colors
+                .get(&entry.path)
+                .copied()
+                .unwrap_or(filetypes::Category::Other);
+            Style::default()
+                .fg(app.theme.background)
+                .bg(category_color(&app.theme, category))
+        };
+
+        // Leave a column and a row of background between neighbours where there is room
+        let mut tile = rect;
+        if tile.width > 2 {
+            tile.width -= 1;
+        }
+        if tile.height > 1 {
+            tile.height -= 1;
+        }
+
+        let checkbox = if app.selected_paths.contains(&entry.path) { "[X] " } else { "" };
+        let slash = if entry.is_dir { "/" } else { "" };
+        let lines = vec![
+            Line::from(format!("{}{}{}", checkbox, entry.name, slash)),
+            Line::from(system::format_bytes(entry.size_in(app.size_mode))),
+        ];
+        f.render_widget(Paragraph::new(lines).style(style), tile);
+    }
+}
+
+fn category_color(theme: &theme::OmarchyTheme, category: filetypes::Category) -> Color {
+    match category {
+        filetypes::Category::Video => theme.color1,
+        filetypes::Category::Images => theme.color2,
+        filetypes::Category::Audio => theme.color3,
+        filetypes::Category::Archives => theme.color4,
+        filetypes::Category::Documents => theme.color5,
+        filetypes::Category::Code => theme.color6,
+        filetypes::Category::Binaries => theme.color9,
+        filetypes::Category::VmDisks => theme.color13,
+        filetypes::Category::Other => theme.color7,
+    }
+}
+
+fn render_type_panel(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let total: u64 = app
+        .type_categories
+        .iter()
+        .map(|(_, totals)| totals.size_in(app.size_mode))
+        .sum();
+
+    let lines: Vec<Line> = app
+        .type_categories
+        .iter()
+        .map(|(category, totals)| {
+            let size = totals.size_in(app.size_mode);
+            let percent = (size * 100).checked_div(total).unwrap_or(0);
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<10}", category.label()),
+                    Style::default().fg(category_color(&app.theme, *category)),
+                ),
+                Span::raw(format!(
+                    "{:>10} {:>3}%  {} files",
+                    system::format_bytes(size),
+                    percent,
+                    totals.files
+                )),
+            ])
+        })
+        .collect();
+
+    let panel = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" By category ")
+                .borders(Borders::RIGHT | Borders::BOTTOM)
+                .border_style(Style::default().fg(app.theme.color8)),
         )
-        .highlight_symbol(">> ");
+        .style(Style::default().fg(app.theme.foreground));
+    f.render_widget(panel, area);
+}
 
-    f.render_stateful_widget(list, area, &mut state);
+// An extension row: its category, the extension, how many files and their total size
+fn format_type_row(app: &App, entry: &scanner::DirEntry, max_name_len: usize) -> Line<'static> {
+    let category = filetypes::Category::of(&entry.name);
+    let name = if entry.name == "(none)" {
+        "(no extension)".to_string()
+    } else {
+        format!(".{}", entry.name)
+    };
+    let name: String = name.chars().take(max_name_len).collect();
+    let padding = " ".repeat(max_name_len.saturating_sub(name.chars().count()));
+
+    Line::from(vec![
+        Span::styled(
+            format!(" {:<11}", category.label()),
+            Style::default().fg(category_color(&app.theme, category)),
+        ),
+        Span::raw(format!(
+            "{}{} {:>13} {}",
+            name,
+            padding,
+            format!("{} files", entry.file_count),
+            system::format_bytes(entry.size_in(app.size_mode))
+        )),
+    ])
+}
+
+fn format_date(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|d| {
+            d.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+// "+1.2 GB" / "-300.0 MB"
+fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, system::format_bytes(delta.unsigned_abs()))
+}
+
+fn format_scan_entry(app: &App, index: usize, max_name_len: usize) -> Line<'static> {
+    let entry = &app.scan_results[index];
+    let is_selected = app.selected_paths.contains(&entry.path);
+    let checkbox = if is_selected { "[X]" } else { "[ ]" };
+    // Copies of the same file share a group number
+    let group = (app.scanner_mode == ScannerMode::Duplicates)
+        .then(|| app.row_group.get(index))
+        .flatten();
+    let prefix = if let Some(group) = group {
+        format!("#{}", group + 1)
+    } else if entry.is_mount {
+        "[MNT]".to_string()
+    } else if entry.is_dir {
+        "[DIR]".to_string()
+    } else {
+        "[FILE]".to_string()
+    };
+    let mut size_str = system::format_bytes(entry.size_in(app.size_mode));
+    if entry.shared {
+        // Other hard links keep this data alive, trashing it won't free it all
+        size_str.push_str(" [LINKED]");
+    }
+
+    let change = (app.scanner_mode == ScannerMode::Changes)
+        .then(|| app.scan_changes.get(index))
+        .flatten();
+    let mut display_name = entry.name.clone();
+    if change.is_some_and(|c| c.gone) {
+        display_name.push_str(" (gone)");
+    }
+    if display_name.chars().count() > max_name_len && max_name_len > 3 {
+        display_name = display_name.chars().take(max_name_len - 3).collect();
+        display_name.push_str("...");
+    }
+    let name_len = display_name.chars().count();
+
+    // Highlight the characters the search query matched
+    let matched = if app.search_query.is_empty() {
+        Vec::new()
+    } else {
+        scanner::fuzzy_match(&app.search_query, &entry.name).unwrap_or_default()
+    };
+    let match_style = Style::default()
+        .fg(app.theme.color3)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = vec![Span::raw(format!(" {} {:<6} ", checkbox, prefix))];
+    for (i, c) in display_name.chars().enumerate() {
+        if matched.contains(&i) {
+            spans.push(Span::styled(c.to_string(), match_style));
+        } else {
+            spans.push(Span::raw(c.to_string()));
+        }
+    }
+
+    let detail = match app.sort_key {
+        _ if let Some(&group) = group => {
+            format!("{} copies", app.duplicate_groups[group].files.len())
+        }
+        _ if app.scanner_mode == ScannerMode::Stale => format!("used {}", format_date(entry.last_used())),
+        _ if let Some(change) = change => format_delta(change.delta),
+        scanner::SortKey::Modified => format_date(entry.mtime),
+        scanner::SortKey::Items if entry.is_dir => format!("{} files", entry.file_count),
+        _ => String::new(),
+    };
+    let padding = " ".repeat(max_name_len.saturating_sub(name_len));
+    // Growth in red, shrinking in green
+    let detail_style = match change {
+        Some(change) if change.delta > 0 => Style::default().fg(app.theme.color1),
+        Some(_) => Style::default().fg(app.theme.color2),
+        None => Style::default(),
+    };
+    if detail.is_empty() {
+        spans.push(Span::raw(format!("{} {}", padding, size_str)));
+    } else {
+        spans.push(Span::raw(format!("{} ", padding)));
+        spans.push(Span::styled(format!("{:>13}", detail), detail_style));
+        spans.push(Span::raw(format!(" {}", size_str)));
+    }
+
+    Line::from(spans)
 }
 
 fn render_session_trash_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
-    if app.trashed_items.is_empty() {
+    if app.browse_trash {
+        render_trash_browser(f, app, area);
+        return;
+    }
+    if app.session_trash_len() == 0 {
         let p = Paragraph::new(
             "\n\nSession Trash is empty.\n(Items trashed in the Deep Scanner will appear here)",
         )
@@ -840,6 +3073,17 @@ fn render_session_trash_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rec
         items.push(ListItem::new(text).style(style));
     }
 
+    for item in app.deduped_items.iter() {
+        let text = format!(
+            " [{}] {} -> {} ({}) ",
+            item.kind.label(),
+            item.path.display(),
+            item.kept.display(),
+            system::format_bytes(item.size)
+        );
+        items.push(ListItem::new(text).style(Style::default().fg(app.theme.foreground)));
+    }
+
     let mut state = ratatui::widgets::ListState::default();
     state.select(Some(app.session_trash_index));
 
@@ -858,7 +3102,106 @@ fn render_session_trash_tab(f: &mut Frame, app: &App, area: ratatui::layout::Rec
         )
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(list, area, &mut state);
+    let Some(message) = &app.trash_message else {
+        f.render_stateful_widget(list, area, &mut state);
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+    f.render_stateful_widget(list, chunks[0], &mut state);
+    let status_line = Paragraph::new(format!(" {}", message)).style(Style::default().fg(app.theme.color7));
+    f.render_widget(status_line, chunks[1]);
+}
+
+fn render_trash_browser(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+
+    let mut title = format!(" Whole Trash, deleted {} ", app.trash_age.label());
+    if !app.trash_filter.is_empty() || app.trash_filter_input {
+        let cursor = if app.trash_filter_input { "_" } else { "" };
+        title = format!("{}| location: {}{} ", title, app.trash_filter, cursor);
+    }
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::BOTTOM)
+        .border_style(Style::default().fg(app.theme.color8));
+
+    if app.trash_view.is_empty() {
+        let text = if app.trash_entries_rx.is_some() {
+            "\n\nReading the trash..."
+        } else if app.trash_entries.is_empty() {
+            "\n\nThe trash is empty."
+        } else {
+            "\n\nNothing in the trash matches the filters.\n([/] changes the location filter, [d] the age)"
+        };
+        let p = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(app.theme.foreground))
+            .block(block);
+        f.render_widget(p, chunks[0]);
+    } else {
+        let max_name_len = (chunks[0].width as usize).saturating_sub(36);
+        let items: Vec<ListItem> = app
+            .trash_view
+            .iter()
+            .map(|&i| {
+                let entry = &app.trash_entries[i];
+                let date = entry
+                    .deletion_date
+                    .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "unknown date".to_string());
+                let mut display_name = entry.original_path.to_string_lossy().into_owned();
+                if entry.is_dir {
+                    display_name.push('/');
+                }
+                if display_name.len() > max_name_len && max_name_len > 3 {
+                    let overflow = display_name.len() - max_name_len + 3;
+                    let cut = (overflow..display_name.len())
+                        .find(|&i| display_name.is_char_boundary(i))
+                        .unwrap_or(display_name.len());
+                    display_name.replace_range(0..cut, "...");
+                }
+                let text = format!(
+                    " {:<16}  {:>10}  {}",
+                    date,
+                    system::format_bytes(entry.size),
+                    display_name
+                );
+                ListItem::new(text).style(Style::default().fg(app.theme.foreground))
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(app.trash_browser_index));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .fg(app.theme.background)
+                    .bg(app.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[0], &mut state);
+    }
+
+    let shown: u64 = app.trash_view.iter().map(|&i| app.trash_entries[i].size).sum();
+    let mut status = format!(
+        " {} of {} items shown, {}",
+        app.trash_view.len(),
+        app.trash_entries.len(),
+        system::format_bytes(shown)
+    );
+    if let Some(message) = &app.trash_message {
+        status = format!("{}   {}", status, message);
+    }
+    let status_line = Paragraph::new(status).style(Style::default().fg(app.theme.color7));
+    f.render_widget(status_line, chunks[1]);
 }
 
 fn format_target(name: &str, size: u64, selected: bool) -> String {
@@ -1,66 +1,995 @@
-use jwalk::WalkDir;
+use crate::config::GlobPattern;
+use crate::filetypes::{extension_of, Category, TypeTotals};
+use jwalk::WalkDirGeneric;
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// How often the worker pushes partial results to the UI
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Clone, Debug)]
 pub struct DirEntry {
     pub path: PathBuf,
     pub name: String,
+    /// Apparent size, the sum of file lengths
     pub size: u64,
+    /// Allocated size on disk (st_blocks * 512), what deleting would actually free
+    pub disk_size: u64,
     pub is_dir: bool,
+    /// Holds hard links whose other names live outside of this entry, so
+    /// trashing it won't free all of its space
+    pub shared: bool,
+    /// A different filesystem is mounted here
+    pub is_mount: bool,
+    /// Number of files inside (1 for a file)
+    pub file_count: u64,
+    /// Most recent modification anywhere inside, as a unix timestamp
+    pub mtime: i64,
+    /// Most recent file access anywhere inside, as a unix timestamp
+    pub atime: i64,
 }
 
-pub fn scan_directory(path: &Path) -> Vec<DirEntry> {
-    if !path.exists() {
-        return Vec::new();
+impl DirEntry {
+    pub fn size_in(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Allocated => self.disk_size,
+            SizeMode::Apparent => self.size,
+        }
     }
 
-    let mut direct_children = HashMap::new();
-    let root_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    /// When anything inside was last modified or read
+    pub fn last_used(&self) -> i64 {
+        self.mtime.max(self.atime)
+    }
+}
 
-    for entry in WalkDir::new(&root_path).skip_hidden(false) {
-        if let Ok(entry) = entry
-            && let Ok(metadata) = entry.metadata()
-                && metadata.is_file() {
-                    let file_size = metadata.len();
-
-                    let file_path = entry.path();
-                    if let Ok(rel) = file_path.strip_prefix(&root_path) {
-                        if let Some(first_component) = rel.components().next() {
-                            let mut child_path = root_path.clone();
-                            child_path.push(first_component);
-
-                            let entry_size = direct_children.entry(child_path).or_insert(0);
-                            *entry_size += file_size;
-                        } else {
-                            // The file is directly in the root_path
-                            let entry_size = direct_children.entry(file_path.clone()).or_insert(0);
-                            *entry_size += file_size;
-                        }
-                    }
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizeMode {
+    #[default]
+    Allocated,
+    Apparent,
+}
+
+impl SizeMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            SizeMode::Allocated => SizeMode::Apparent,
+            SizeMode::Apparent => SizeMode::Allocated,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeMode::Allocated => "on disk",
+            SizeMode::Apparent => "apparent",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Size,
+    Name,
+    Modified,
+    Items,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Size => SortKey::Name,
+            SortKey::Name => SortKey::Modified,
+            SortKey::Modified => SortKey::Items,
+            SortKey::Items => SortKey::Size,
+        }
+    }
+
+    /// Whether this key is most useful biggest/newest first
+    pub fn descending_by_default(self) -> bool {
+        match self {
+            SortKey::Size | SortKey::Items => true,
+            // A-Z, and the longest untouched folders first
+            SortKey::Name | SortKey::Modified => false,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Size => "size",
+            SortKey::Name => "name",
+            SortKey::Modified => "last modified",
+            SortKey::Items => "item count",
+        }
+    }
+}
+
+pub fn sort_entries(entries: &mut [DirEntry], key: SortKey, descending: bool, mode: SizeMode) {
+    entries.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Size => a.size_in(mode).cmp(&b.size_in(mode)),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Modified => a.mtime.cmp(&b.mtime),
+            SortKey::Items => a.file_count.cmp(&b.file_count),
+        };
+        // Fall back to the name so equal keys keep a stable, predictable order
+        let ordering = ordering.then_with(|| a.name.cmp(&b.name));
+        if descending { ordering.reverse() } else { ordering }
+    });
+}
+
+/// Case-insensitive subsequence match of `query` against `name`.
+/// Returns the char positions in `name` that matched, for highlighting.
+pub fn fuzzy_match(query: &str, name: &str) -> Option<Vec<usize>> {
+    let mut positions = Vec::new();
+    let mut wanted = query.chars().flat_map(char::to_lowercase).peekable();
+
+    for (i, c) in name.chars().enumerate() {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            positions.push(i);
+            wanted.next();
+        }
+    }
+
+    wanted.peek().is_none().then_some(positions)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Don't descend into other mounted filesystems, like `du -x`
+    pub one_filesystem: bool,
+    /// Paths that are left out of the tree and only counted towards the excluded total
+    pub exclude: Arc<Vec<GlobPattern>>,
+}
+
+/// Stale files and folders that all live in the same top-level folder
+#[derive(Clone, Debug)]
+pub struct StaleGroup {
+    pub name: String,
+    pub entries: Vec<DirEntry>,
+}
+
+impl StaleGroup {
+    pub fn size_in(&self, mode: SizeMode) -> u64 {
+        self.entries.iter().map(|e| e.size_in(mode)).sum()
+    }
+}
+
+/// A path skipped because of an exclude rule, with the space hidden underneath it
+#[derive(Clone, Debug)]
+pub struct ExcludedPath {
+    pub path: PathBuf,
+    pub size: u64,
+    pub disk_size: u64,
+}
+
+// What process_read_dir found out about an entry before the scan loop sees it
+#[derive(Debug, Default)]
+struct EntryFlags {
+    is_mount: bool,
+    excluded: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScanProgress {
+    pub files: u64,
+    pub bytes: u64,
+    pub disk_bytes: u64,
+}
+
+impl ScanProgress {
+    pub fn bytes_in(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Allocated => self.disk_bytes,
+            SizeMode::Apparent => self.bytes,
+        }
+    }
+}
+
+pub enum ScanUpdate {
+    Partial(ScanProgress, Vec<DirEntry>),
+    Finished(ScanProgress, ScanTree),
+    Cancelled(ScanProgress, ScanTree),
+}
+
+// Totals that roll up from every file into all the directories above it
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats {
+    size: u64,
+    disk_size: u64,
+    files: u64,
+    mtime: i64,
+    atime: i64,
+}
+
+struct Node {
+    name: OsString,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    stats: Stats,
+    is_dir: bool,
+    shared: bool,
+    is_mount: bool,
+    // A directory holding something the tree leaves out (symlinks, sockets,
+    // excluded or unreadable entries), which keeps it from being empty
+    untracked: bool,
+    // A file without a single byte. Its size alone can't tell, extra hard
+    // links are counted as 0 bytes.
+    zero_length: bool,
+}
+
+// Allocated bytes as reported by st_blocks, which is always in 512-byte units
+fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.blocks() * 512
+}
+
+/// Every file and directory below `root` with its accumulated size.
+/// Nodes live in a flat arena; directories are also indexed by path so the
+/// UI can jump straight to any folder without walking the disk again.
+pub struct ScanTree {
+    root: PathBuf,
+    nodes: Vec<Node>,
+    dirs: HashMap<PathBuf, usize>,
+    excluded: Vec<ExcludedPath>,
+}
+
+impl ScanTree {
+    /// A tree holding nothing but `root`, for `add_entry` to fill in.
+    pub fn new(root: PathBuf) -> Self {
+        let name = root.file_name().unwrap_or(root.as_os_str()).to_os_string();
+        let mut dirs = HashMap::new();
+        dirs.insert(root.clone(), 0);
+
+        Self {
+            root,
+            nodes: vec![Node {
+                name,
+                parent: None,
+                children: Vec::new(),
+                stats: Stats::default(),
+                is_dir: true,
+                shared: false,
+                is_mount: false,
+                untracked: false,
+                zero_length: false,
+            }],
+            dirs,
+            excluded: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn contains_dir(&self, path: &Path) -> bool {
+        self.dirs.contains_key(path)
+    }
+
+    pub fn dir_size(&self, path: &Path, mode: SizeMode) -> Option<u64> {
+        self.dirs.get(path).map(|&idx| match mode {
+            SizeMode::Allocated => self.nodes[idx].stats.disk_size,
+            SizeMode::Apparent => self.nodes[idx].stats.size,
+        })
+    }
+
+    /// Number of excluded paths below `dir` and the space they take up.
+    pub fn excluded_under(&self, dir: &Path, mode: SizeMode) -> (usize, u64) {
+        self.excluded
+            .iter()
+            .filter(|e| e.path.starts_with(dir))
+            .fold((0, 0), |(count, bytes), e| {
+                let size = match mode {
+                    SizeMode::Allocated => e.disk_size,
+                    SizeMode::Apparent => e.size,
+                };
+                (count + 1, bytes + size)
+            })
+    }
+
+    /// The file or folder at `path` with its totals, named like its path.
+    pub fn entry(&self, path: &Path) -> Option<DirEntry> {
+        let idx = self.find(path)?;
+        Some(self.entry_for(idx, path.to_path_buf(), path.to_string_lossy().to_string()))
+    }
+
+    /// Adds `entry` below its folder, which has to be in the tree already, and
+    /// returns false if it isn't. Its sizes are its own and get added to every
+    /// folder above it, like a scan would count them. Mount points stay
+    /// unindexed, as if the scan had stayed on one filesystem.
+    pub fn add_entry(&mut self, entry: &DirEntry) -> bool {
+        let stats = Stats {
+            size: entry.size,
+            disk_size: entry.disk_size,
+            files: u64::from(!entry.is_dir),
+            mtime: entry.mtime,
+            atime: entry.atime,
+        };
+        if entry.path == self.root {
+            self.add_stats(0, stats);
+            return true;
+        }
+        let (Some(&parent), Some(name)) = (
+            entry.path.parent().and_then(|p| self.dirs.get(p)),
+            entry.path.file_name(),
+        ) else {
+            return false;
+        };
+
+        let idx = self.add_node(parent, name.to_os_string(), entry.is_dir, stats);
+        self.nodes[idx].is_mount = entry.is_mount;
+        self.nodes[idx].shared = entry.shared;
+        if entry.is_dir && !entry.is_mount {
+            self.dirs.insert(entry.path.clone(), idx);
+        }
+        true
+    }
+
+    /// Direct children of `dir`, in no particular order (see `sort_entries`).
+    pub fn entries(&self, dir: &Path) -> Option<Vec<DirEntry>> {
+        let &idx = self.dirs.get(dir)?;
+
+        let results: Vec<DirEntry> = self.nodes[idx]
+            .children
+            .iter()
+            .map(|&child| {
+                let node = &self.nodes[child];
+                self.entry_for(child, dir.join(&node.name), node.name.to_string_lossy().to_string())
+            })
+            .collect();
+        Some(results)
+    }
+
+    /// The `limit` biggest files anywhere below `dir`, named by their path relative to it.
+    pub fn largest_files(&self, dir: &Path, limit: usize, mode: SizeMode) -> Vec<DirEntry> {
+        let Some(&start) = self.dirs.get(dir) else {
+            return Vec::new();
+        };
+
+        // Min-heap of the best candidates so far, the smallest gets evicted first
+        let mut heap = std::collections::BinaryHeap::new();
+        for idx in self.descendants(start) {
+            let node = &self.nodes[idx];
+            if node.is_dir {
+                continue;
+            }
+            let size = match mode {
+                SizeMode::Allocated => node.stats.disk_size,
+                SizeMode::Apparent => node.stats.size,
+            };
+            heap.push(std::cmp::Reverse((size, idx)));
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+
+        heap.into_iter()
+            .map(|std::cmp::Reverse((_, idx))| {
+                let path = self.node_path(idx);
+                let name = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                self.entry_for(idx, path, name)
+            })
+            .collect()
+    }
+
+    /// Files below `dir` grouped by apparent size, keeping only sizes shared by
+    /// more than one file. Empty files and extra hard links (counted as 0 bytes)
+    /// never show up. Names are relative to `dir`.
+    pub fn same_size_files(&self, dir: &Path) -> Vec<Vec<DirEntry>> {
+        let Some(&start) = self.dirs.get(dir) else {
+            return Vec::new();
+        };
+
+        let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+        for idx in self.descendants(start) {
+            let node = &self.nodes[idx];
+            if !node.is_dir && node.stats.size > 0 {
+                by_size.entry(node.stats.size).or_default().push(idx);
+            }
+        }
+
+        by_size
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|idx| {
+                        let path = self.node_path(idx);
+                        let name = path
+                            .strip_prefix(dir)
+                            .unwrap_or(&path)
+                            .to_string_lossy()
+                            .to_string();
+                        self.entry_for(idx, path, name)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Everything below `dir` that hasn't been modified or read since `cutoff`
+    /// (a unix timestamp), grouped by the top-level folder it lives in. A folder
+    /// with nothing newer inside is listed as a whole rather than file by file.
+    pub fn stale_entries(&self, dir: &Path, cutoff: i64) -> Vec<StaleGroup> {
+        let Some(&start) = self.dirs.get(dir) else {
+            return Vec::new();
+        };
+
+        let mut groups = Vec::new();
+        for &top in &self.nodes[start].children {
+            let mut entries = Vec::new();
+            let mut stack = vec![top];
+            while let Some(idx) = stack.pop() {
+                let node = &self.nodes[idx];
+                // Other filesystems may not have been scanned, their times mean nothing
+                if node.is_mount {
+                    continue;
+                }
+                if node.stats.mtime.max(node.stats.atime) < cutoff {
+                    let path = self.node_path(idx);
+                    let name = path
+                        .strip_prefix(dir)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .to_string();
+                    entries.push(self.entry_for(idx, path, name));
+                } else {
+                    stack.extend_from_slice(&node.children);
                 }
+            }
+            if !entries.is_empty() {
+                groups.push(StaleGroup {
+                    name: self.nodes[top].name.to_string_lossy().to_string(),
+                    entries,
+                });
+            }
+        }
+        groups
     }
 
-    let mut results = Vec::new();
-    for (child_path, size) in direct_children {
-        let is_dir = child_path.is_dir();
-        let name = child_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+    /// Empty directories (including ones holding nothing but empty directories)
+    /// and zero-byte files below `dir`, named relative to it. An empty directory
+    /// is listed as a whole rather than with everything inside it.
+    pub fn empty_entries(&self, dir: &Path) -> Vec<DirEntry> {
+        let Some(&start) = self.dirs.get(dir) else {
+            return Vec::new();
+        };
 
-        results.push(DirEntry {
-            path: child_path,
+        let mut results = Vec::new();
+        let mut stack = self.nodes[start].children.clone();
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            // Unscanned filesystems look empty without being so
+            if node.is_mount {
+                continue;
+            }
+            let empty = if node.is_dir {
+                self.is_empty_dir(idx)
+            } else {
+                node.zero_length
+            };
+            if empty {
+                let path = self.node_path(idx);
+                let name = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                results.push(self.entry_for(idx, path, name));
+            } else if node.is_dir {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+        results
+    }
+
+    // Nothing but folders below, and nothing the scan left out of the tree
+    fn is_empty_dir(&self, idx: usize) -> bool {
+        let node = &self.nodes[idx];
+        node.stats.files == 0
+            && !node.untracked
+            && !node.is_mount
+            && node.children.iter().all(|&child| self.is_empty_dir(child))
+    }
+
+    fn mark_untracked(&mut self, dir: &Path) {
+        if let Some(&idx) = self.dirs.get(dir) {
+            self.nodes[idx].untracked = true;
+        }
+    }
+
+    /// Every file below `dir` summed up by extension, in no particular order.
+    pub fn type_breakdown(&self, dir: &Path) -> Vec<(String, TypeTotals)> {
+        let Some(&start) = self.dirs.get(dir) else {
+            return Vec::new();
+        };
+
+        let mut by_extension: HashMap<String, TypeTotals> = HashMap::new();
+        for idx in self.descendants(start) {
+            let node = &self.nodes[idx];
+            if node.is_dir {
+                continue;
+            }
+            by_extension
+                .entry(extension_of(&node.name))
+                .or_default()
+                .add(&TypeTotals {
+                    size: node.stats.size,
+                    disk_size: node.stats.disk_size,
+                    files: node.stats.files,
+                });
+        }
+        by_extension.into_iter().collect()
+    }
+
+    /// `dir` and every folder below it, e.g. for the scan index.
+    pub fn directories_under(&self, dir: &Path) -> Vec<DirEntry> {
+        self.dirs
+            .iter()
+            .filter(|(path, _)| path.starts_with(dir))
+            .map(|(path, &idx)| {
+                let name = self.nodes[idx].name.to_string_lossy().to_string();
+                self.entry_for(idx, path.clone(), name)
+            })
+            .collect()
+    }
+
+    /// The kind of file taking up most of the space at `path`.
+    pub fn main_category(&self, path: &Path, mode: SizeMode) -> Category {
+        let Some(idx) = self.find(path) else {
+            return Category::Other;
+        };
+        if !self.nodes[idx].is_dir {
+            return Category::of(&extension_of(&self.nodes[idx].name));
+        }
+
+        let mut totals: HashMap<Category, u64> = HashMap::new();
+        for (extension, type_totals) in self.type_breakdown(path) {
+            *totals.entry(Category::of(&extension)).or_default() += type_totals.size_in(mode);
+        }
+        totals
+            .into_iter()
+            .max_by_key(|&(_, size)| size)
+            .map_or(Category::Other, |(category, _)| category)
+    }
+
+    fn entry_for(&self, idx: usize, path: PathBuf, name: String) -> DirEntry {
+        let node = &self.nodes[idx];
+        DirEntry {
+            path,
             name,
-            size,
+            size: node.stats.size,
+            disk_size: node.stats.disk_size,
+            is_dir: node.is_dir,
+            shared: node.shared,
+            is_mount: node.is_mount,
+            file_count: node.stats.files,
+            mtime: node.stats.mtime,
+            atime: node.stats.atime,
+        }
+    }
+
+    // Every node below `idx`, not including itself
+    fn descendants(&self, idx: usize) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut stack = self.nodes[idx].children.clone();
+        while let Some(i) = stack.pop() {
+            result.push(i);
+            stack.extend_from_slice(&self.nodes[i].children);
+        }
+        result
+    }
+
+    fn node_path(&self, idx: usize) -> PathBuf {
+        let mut chain = self.ancestors(idx);
+        chain.pop(); // the root, which is already the base path
+        let mut path = self.root.clone();
+        for i in chain.into_iter().rev() {
+            path.push(&self.nodes[i].name);
+        }
+        path
+    }
+
+    fn add_node(&mut self, parent: usize, name: OsString, is_dir: bool, stats: Stats) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name,
+            parent: Some(parent),
+            children: Vec::new(),
+            stats: Stats::default(),
             is_dir,
+            shared: false,
+            is_mount: false,
+            untracked: false,
+            zero_length: false,
+        });
+        self.nodes[parent].children.push(idx);
+        self.add_stats(idx, stats);
+        idx
+    }
+
+    // Adds to a node and every directory above it
+    fn add_stats(&mut self, idx: usize, stats: Stats) {
+        let mut current = Some(idx);
+        while let Some(i) = current {
+            let node = &mut self.nodes[i].stats;
+            node.size += stats.size;
+            node.disk_size += stats.disk_size;
+            node.files += stats.files;
+            node.mtime = node.mtime.max(stats.mtime);
+            node.atime = node.atime.max(stats.atime);
+            current = self.nodes[i].parent;
+        }
+    }
+
+    // Takes a removed subtree's totals back out of every directory above it.
+    // The newest mtime and atime are left as is, they would need a full rescan to recompute.
+    fn subtract_stats(&mut self, idx: usize, stats: Stats) {
+        let mut current = Some(idx);
+        while let Some(i) = current {
+            let node = &mut self.nodes[i].stats;
+            node.size = node.size.saturating_sub(stats.size);
+            node.disk_size = node.disk_size.saturating_sub(stats.disk_size);
+            node.files = node.files.saturating_sub(stats.files);
+            current = self.nodes[i].parent;
+        }
+    }
+
+    /// Flags every node that holds some, but not all, links of a hard-linked inode.
+    /// `nlink` is the link count reported by the filesystem, `links` are the file
+    /// nodes the scan actually came across.
+    fn mark_shared(&mut self, nlink: u64, links: &[usize]) {
+        // Nodes that contain every link we saw; with links outside the scan
+        // even the root doesn't, so nothing is exempt
+        let mut common: Vec<usize> = Vec::new();
+        if (links.len() as u64) >= nlink {
+            let mut chains = links.iter().map(|&l| self.ancestors(l));
+            if let Some(first) = chains.next() {
+                common = first;
+                for chain in chains {
+                    common.retain(|i| chain.contains(i));
+                }
+            }
+        }
+
+        for &link in links {
+            for idx in self.ancestors(link) {
+                if common.contains(&idx) {
+                    break;
+                }
+                self.nodes[idx].shared = true;
+            }
+        }
+    }
+
+    // The node itself followed by every directory above it, up to the root
+    fn ancestors(&self, idx: usize) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut current = Some(idx);
+        while let Some(i) = current {
+            chain.push(i);
+            current = self.nodes[i].parent;
+        }
+        chain
+    }
+
+    fn find(&self, path: &Path) -> Option<usize> {
+        if let Some(&idx) = self.dirs.get(path) {
+            return Some(idx);
+        }
+        let &parent = self.dirs.get(path.parent()?)?;
+        let name = path.file_name()?;
+        self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].name == name)
+    }
+
+    /// Drops `path` and everything below it, e.g. after it was moved to the trash.
+    /// The freed arena slots are simply left behind until the next full scan.
+    pub fn remove(&mut self, path: &Path) {
+        let Some(idx) = self.find(path) else {
+            return;
+        };
+        let Some(parent) = self.nodes[idx].parent else {
+            return; // never remove the root itself
+        };
+
+        self.subtract_stats(parent, self.nodes[idx].stats);
+        self.nodes[parent].children.retain(|&child| child != idx);
+        self.excluded.retain(|e| !e.path.starts_with(path));
+        self.unindex(idx, path.to_path_buf());
+    }
+
+    /// `path` was turned into a hard link of `target`, so its space is now only
+    /// counted once, through `target`.
+    pub fn mark_hard_linked(&mut self, path: &Path, target: &Path) {
+        let (Some(idx), Some(target)) = (self.find(path), self.find(target)) else {
+            return;
+        };
+        let stats = Stats {
+            files: 0,
+            ..self.nodes[idx].stats
+        };
+        self.subtract_stats(idx, stats);
+        self.nodes[idx].shared = true;
+        self.nodes[target].shared = true;
+    }
+
+    fn unindex(&mut self, idx: usize, path: PathBuf) {
+        if !self.nodes[idx].is_dir {
+            return;
+        }
+        let children = self.nodes[idx].children.clone();
+        for child in children {
+            let child_path = path.join(&self.nodes[child].name);
+            self.unindex(child, child_path);
+        }
+        self.dirs.remove(&path);
+    }
+
+    /// Replaces the directory at `subtree.root` with a freshly scanned copy.
+    pub fn graft(&mut self, subtree: ScanTree) {
+        if subtree.root == self.root {
+            *self = subtree;
+            return;
+        }
+        let Some(&old) = self.dirs.get(&subtree.root) else {
+            return;
+        };
+        let Some(parent) = self.nodes[old].parent else {
+            return;
+        };
+        self.remove(&subtree.root);
+
+        let offset = self.nodes.len();
+        for (i, mut node) in subtree.nodes.into_iter().enumerate() {
+            node.parent = if i == 0 {
+                Some(parent)
+            } else {
+                node.parent.map(|p| p + offset)
+            };
+            for child in node.children.iter_mut() {
+                *child += offset;
+            }
+            self.nodes.push(node);
+        }
+        for (path, idx) in subtree.dirs {
+            self.dirs.insert(path, idx + offset);
+        }
+        self.excluded.extend(subtree.excluded);
+
+        self.nodes[parent].children.push(offset);
+        self.add_stats(parent, self.nodes[offset].stats);
+    }
+}
+
+/// A scan running on a worker thread. Poll `rx` for updates, drop or cancel to stop it.
+pub struct ScanJob {
+    pub path: PathBuf,
+    pub rx: Receiver<ScanUpdate>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ScanJob {
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ScanJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+pub fn spawn_scan(path: &Path, options: ScanOptions) -> ScanJob {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let worker_path = path.to_path_buf();
+    let worker_cancel = cancel.clone();
+    std::thread::spawn(move || {
+        scan_directory(&worker_path, options, &worker_cancel, &tx);
+    });
+
+    ScanJob {
+        path: path.to_path_buf(),
+        rx,
+        cancel,
+    }
+}
+
+fn scan_directory(path: &Path, options: ScanOptions, cancel: &Arc<AtomicBool>, tx: &Sender<ScanUpdate>) {
+    let mut progress = ScanProgress::default();
+
+    let root_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut tree = ScanTree::new(root_path.clone());
+    if !path.exists() {
+        let _ = tx.send(ScanUpdate::Finished(progress, tree));
+        return;
+    }
+
+    let mut last_update = Instant::now();
+
+    // (device, inode) -> link count and the file nodes pointing at it, so every
+    // hard-linked file is only counted once
+    let mut hard_links: HashMap<(u64, u64), (u64, Vec<usize>)> = HashMap::new();
+
+    // Directories inside an excluded path -> index into tree.excluded
+    let mut excluded_dirs: HashMap<PathBuf, usize> = HashMap::new();
+
+    let walk_cancel = cancel.clone();
+    let walk_options = options.clone();
+    let walker = WalkDirGeneric::<((), EntryFlags)>::new(&root_path)
+        .skip_hidden(false)
+        .process_read_dir(move |_, dir_path, _, children| {
+            // Stop jwalk from reading any further directories once cancelled
+            if walk_cancel.load(Ordering::Relaxed) {
+                children.clear();
+                return;
+            }
+
+            if !walk_options.exclude.is_empty() {
+                for child in children.iter_mut().flatten() {
+                    let child_path = child.path();
+                    child.client_state.excluded =
+                        walk_options.exclude.iter().any(|p| p.matches(&child_path));
+                }
+            }
+
+            let Ok(dir_dev) = std::fs::symlink_metadata(dir_path).map(|m| m.dev()) else {
+                return;
+            };
+            for child in children.iter_mut().flatten() {
+                if child.file_type.is_dir()
+                    && let Ok(metadata) = child.metadata()
+                    && metadata.dev() != dir_dev
+                {
+                    child.client_state.is_mount = true;
+                    if walk_options.one_filesystem {
+                        child.read_children_path = None;
+                    }
+                }
+            }
         });
+
+    for entry in walker {
+        if cancel.load(Ordering::Relaxed) {
+            finish_hard_links(&mut tree, &hard_links);
+            let _ = tx.send(ScanUpdate::Cancelled(progress, tree));
+            return;
+        }
+
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A directory that couldn't be read, or an entry in it that couldn't be looked at
+            Err(e) => {
+                if let Some(path) = e.path() {
+                    tree.mark_untracked(path);
+                    if let Some(parent) = path.parent() {
+                        tree.mark_untracked(parent);
+                    }
+                }
+                continue;
+            }
+        };
+
+        // Everything below an exclude rule only adds to that rule's total
+        let excluded = if let Some(&ex) = excluded_dirs.get(entry.parent_path()) {
+            Some(ex)
+        } else if entry.client_state.excluded && tree.dirs.contains_key(entry.parent_path()) {
+            tree.mark_untracked(entry.parent_path());
+            tree.excluded.push(ExcludedPath {
+                path: entry.path(),
+                size: 0,
+                disk_size: 0,
+            });
+            Some(tree.excluded.len() - 1)
+        } else {
+            None
+        };
+
+        if let Some(ex) = excluded {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    tree.excluded[ex].size += metadata.len();
+                }
+                tree.excluded[ex].disk_size += allocated_size(&metadata);
+            }
+            if entry.file_type().is_dir() {
+                excluded_dirs.insert(entry.path(), ex);
+            }
+        } else if entry.depth() > 0
+            && let Some(&parent) = tree.dirs.get(entry.parent_path())
+        {
+            let file_type = entry.file_type();
+            if file_type.is_dir() && entry.client_state.is_mount && options.one_filesystem {
+                // Listed so the boundary is visible, but left unindexed so opening
+                // it starts a fresh scan of that filesystem
+                let idx = tree.add_node(parent, entry.file_name().to_os_string(), true, Stats::default());
+                tree.nodes[idx].is_mount = true;
+            } else if file_type.is_dir() {
+                // Directories take up blocks of their own, count them like du does.
+                // Their atime is left out, the scan itself just read every directory.
+                let stats = entry
+                    .metadata()
+                    .map(|m| Stats {
+                        disk_size: allocated_size(&m),
+                        mtime: m.mtime(),
+                        ..Default::default()
+                    })
+                    .unwrap_or_default();
+                progress.disk_bytes += stats.disk_size;
+                let idx = tree.add_node(parent, entry.file_name().to_os_string(), true, stats);
+                tree.nodes[idx].is_mount = entry.client_state.is_mount;
+                tree.dirs.insert(entry.path(), idx);
+            } else if file_type.is_file()
+                && let Ok(metadata) = entry.metadata() {
+                    let mut stats = Stats {
+                        size: metadata.len(),
+                        disk_size: allocated_size(&metadata),
+                        files: 1,
+                        mtime: metadata.mtime(),
+                        atime: metadata.atime(),
+                    };
+                    let links = (metadata.nlink() > 1).then(|| {
+                        hard_links
+                            .entry((metadata.dev(), metadata.ino()))
+                            .or_insert_with(|| (metadata.nlink(), Vec::new()))
+                    });
+                    if let Some((_, seen)) = &links
+                        && !seen.is_empty() {
+                            // Already counted through another link
+                            stats.size = 0;
+                            stats.disk_size = 0;
+                        }
+
+                    progress.files += 1;
+                    progress.bytes += stats.size;
+                    progress.disk_bytes += stats.disk_size;
+                    let idx = tree.add_node(parent, entry.file_name().to_os_string(), false, stats);
+                    tree.nodes[idx].zero_length = metadata.len() == 0;
+                    if let Some((_, seen)) = links {
+                        seen.push(idx);
+                    }
+                } else {
+                    // Symlinks, sockets and the like, or a file that couldn't be looked at
+                    tree.nodes[parent].untracked = true;
+                }
+        }
+
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            last_update = Instant::now();
+            let entries = tree.entries(&root_path).unwrap_or_default();
+            if tx.send(ScanUpdate::Partial(progress, entries)).is_err() {
+                // The UI dropped the job, nobody is listening anymore
+                return;
+            }
+        }
     }
 
-    // Sort by size descending
-    results.sort_by(|a, b| b.size.cmp(&a.size));
+    finish_hard_links(&mut tree, &hard_links);
+    let _ = tx.send(ScanUpdate::Finished(progress, tree));
+}
 
-    // Only return top 50
-    results.into_iter().take(50).collect()
+fn finish_hard_links(tree: &mut ScanTree, hard_links: &HashMap<(u64, u64), (u64, Vec<usize>)>) {
+    for (nlink, links) in hard_links.values() {
+        tree.mark_shared(*nlink, links);
+    }
 }
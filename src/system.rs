@@ -1,5 +1,9 @@
+use crate::trash;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
 use sysinfo::Disks;
 
 pub struct DiskUsage {
@@ -24,14 +28,58 @@ pub struct TrashedItem {
     pub original_path: PathBuf,
     pub trash_file_path: PathBuf,
     pub trash_info_path: PathBuf,
+    /// What the info file's path is relative to, `/` for the home trash
+    pub topdir: PathBuf,
     pub is_root: bool,
 }
 
-pub fn move_to_trash(original_path: &Path) -> Result<TrashedItem, String> {
-    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
-    let is_root = !original_path.starts_with(&home_dir);
-
-    if is_root {
+pub enum TrashUpdate {
+    /// A copy into the trash on another filesystem is under way
+    Copying(PathBuf, trash::MoveProgress),
+    Trashed(PathBuf, Result<TrashedItem, String>),
+    /// Something restored from the trash is being copied back to another filesystem
+    CopyingBack(PathBuf, trash::MoveProgress),
+    /// The info file of what was restored, and where it went
+    Restored(PathBuf, Result<PathBuf, String>),
+    Finished,
+}
+
+/// Trashes `paths` one after the other on a worker thread, copies across
+/// filesystems can take a while
+pub fn spawn_trash(paths: Vec<PathBuf>) -> Receiver<TrashUpdate> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for path in paths {
+            let mut report = |progress| {
+                let _ = tx.send(TrashUpdate::Copying(path.clone(), progress));
+            };
+            let result = move_to_trash(&path, &mut report);
+            let _ = tx.send(TrashUpdate::Trashed(path, result));
+        }
+        let _ = tx.send(TrashUpdate::Finished);
+    });
+    rx
+}
+
+/// Restores an item on a worker thread, like trashing it may mean a copy
+pub fn spawn_restore(trash_file_path: PathBuf, trash_info_path: PathBuf, topdir: PathBuf) -> Receiver<TrashUpdate> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut report = |progress| {
+            let _ = tx.send(TrashUpdate::CopyingBack(trash_file_path.clone(), progress));
+        };
+        let result = trash::restore(&trash_file_path, &trash_info_path, &topdir, &mut report);
+        let _ = tx.send(TrashUpdate::Restored(trash_info_path, result));
+        let _ = tx.send(TrashUpdate::Finished);
+    });
+    rx
+}
+
+pub fn move_to_trash(
+    original_path: &Path,
+    report: &mut dyn FnMut(trash::MoveProgress),
+) -> Result<TrashedItem, String> {
+    if !trash::can_trash(original_path) {
         // We do permanent deletion immediately if it's root
         let status = Command::new("pkexec")
             .arg("rm")
@@ -46,76 +94,34 @@ pub fn move_to_trash(original_path: &Path) -> Result<TrashedItem, String> {
                 original_path: original_path.to_path_buf(),
                 trash_file_path: PathBuf::new(),
                 trash_info_path: PathBuf::new(),
+                topdir: PathBuf::new(),
                 is_root: true,
             }),
             _ => Err("Failed to pkexec rm -rf".to_string()),
         }
     } else {
-        let mut trash_dir =
-            dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("~/.local/share"));
-        trash_dir.push("Trash");
-
-        let files_dir = trash_dir.join("files");
-        let info_dir = trash_dir.join("info");
-
-        std::fs::create_dir_all(&files_dir).map_err(|e| e.to_string())?;
-        std::fs::create_dir_all(&info_dir).map_err(|e| e.to_string())?;
-
-        let file_name = original_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .into_owned();
-        let mut safe_name = file_name.clone();
-        let mut counter = 1;
-
-        while files_dir.join(&safe_name).exists()
-            || info_dir.join(format!("{}.trashinfo", safe_name)).exists()
-        {
-            safe_name = format!("{}_{}", file_name, counter);
-            counter += 1;
-        }
-
-        let trash_file_path = files_dir.join(&safe_name);
-        let trash_info_path = info_dir.join(format!("{}.trashinfo", safe_name));
-
-        let now = chrono::Local::now();
-        let date_str = now.format("%Y-%m-%dT%H:%M:%S").to_string();
-        let info_content = format!(
-            "[Trash Info]\nPath={}\nDeletionDate={}\n",
-            original_path.display(),
-            date_str
-        );
-
-        std::fs::write(&trash_info_path, info_content).map_err(|e| e.to_string())?;
-
-        if let Err(e) = std::fs::rename(original_path, &trash_file_path) {
-            let _ = std::fs::remove_file(&trash_info_path);
-            return Err(format!("Failed to move to trash: {}", e));
-        }
+        let (trash_file_path, trash_info_path, topdir) = trash::trash(original_path, report)?;
 
         Ok(TrashedItem {
             original_path: original_path.to_path_buf(),
             trash_file_path,
             trash_info_path,
+            topdir,
             is_root: false,
         })
     }
 }
 
-pub fn restore_trash_item(item: &TrashedItem) -> Result<(), String> {
+pub fn restore_trash_item(item: &TrashedItem) -> Result<Receiver<TrashUpdate>, String> {
     if item.is_root {
         return Err("Cannot restore permanently deleted root files".to_string());
     }
 
-    if let Some(parent) = item.original_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-
-    std::fs::rename(&item.trash_file_path, &item.original_path).map_err(|e| e.to_string())?;
-    let _ = std::fs::remove_file(&item.trash_info_path);
-
-    Ok(())
+    Ok(spawn_restore(
+        item.trash_file_path.clone(),
+        item.trash_info_path.clone(),
+        item.topdir.clone(),
+    ))
 }
 
 pub fn perm_delete_trash_item(item: &TrashedItem) -> Result<(), String> {
@@ -176,8 +182,7 @@ pub fn get_journal_size() -> u64 {
 }
 
 pub fn get_trash_size() -> u64 {
-    let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("~/.local/share"));
-    path.push("Trash");
+    let path = trash::home_trash();
     get_dir_size_with_du(&path.to_string_lossy())
 }
 
@@ -307,6 +312,316 @@ pub fn clean_cargo_cache() -> bool {
     true
 }
 
+/// Regenerable output of one project, like its `target/` or `node_modules/`.
+/// Python caches are scattered all over a project, so they come as one entry
+/// holding every `__pycache__` directory.
+#[derive(Clone, Debug)]
+pub struct BuildArtifact {
+    pub project: PathBuf,
+    pub name: &'static str,
+    pub paths: Vec<PathBuf>,
+    pub size: u64,
+    /// Newest modification among the project's own files, as a unix timestamp
+    pub last_activity: i64,
+    /// For Cargo `target/` directories, the parts that can be cleaned on their own
+    pub parts: Vec<TargetPart>,
+}
+
+/// Output of one kind inside a Cargo `target/` directory, possibly spread over
+/// several folders (one per target triple)
+#[derive(Clone, Debug)]
+pub struct TargetPart {
+    pub name: &'static str,
+    pub paths: Vec<PathBuf>,
+    pub size: u64,
+}
+
+// Profile sizes leave out their incremental data, which is a part of its own
+const TARGET_PARTS: [&str; 4] = ["debug", "release", "incremental", "doc"];
+
+const PROJECT_MARKERS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+];
+
+const PYTHON_MARKERS: &[&str] = &["pyproject.toml", "setup.py"];
+const GRADLE_MARKERS: &[&str] = &[
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+];
+
+// Artifact directory name -> markers that have to sit right next to it for
+// the directory to count as build output rather than something hand-made
+const ARTIFACT_RULES: &[(&str, &[&str])] = &[
+    ("target", &["Cargo.toml"]),
+    ("node_modules", &["package.json"]),
+    (".venv", PYTHON_MARKERS),
+    ("build", GRADLE_MARKERS),
+    (".gradle", GRADLE_MARKERS),
+];
+
+// Deep enough for nested workspaces, shallow enough to not crawl everything
+const ARTIFACT_SEARCH_DEPTH: usize = 8;
+
+/// Walks `roots` for projects (recognised by their marker files) and lists the
+/// build artifacts inside them, biggest first.
+pub fn find_build_artifacts(roots: &[PathBuf]) -> Vec<BuildArtifact> {
+    let mut artifacts = Vec::new();
+    let mut activity = HashMap::new();
+    for root in roots {
+        find_artifacts_in(root, 0, &mut Vec::new(), &mut artifacts, &mut activity);
+    }
+
+    for artifact in &mut artifacts {
+        artifact.last_activity = activity.get(&artifact.project).copied().unwrap_or(0);
+    }
+    artifacts.sort_by_key(|a| std::cmp::Reverse(a.size));
+    artifacts
+}
+
+// `projects` holds the project roots above `dir`, innermost last, and whether each is a Python one
+fn find_artifacts_in(
+    dir: &Path,
+    depth: usize,
+    projects: &mut Vec<(PathBuf, bool)>,
+    artifacts: &mut Vec<BuildArtifact>,
+    activity: &mut HashMap<PathBuf, i64>,
+) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let entries: Vec<_> = read_dir.flatten().collect();
+    let names: Vec<String> = entries
+        .iter()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    let has = |markers: &[&str]| markers.iter().any(|m| names.iter().any(|n| n == m));
+
+    let is_project = has(PROJECT_MARKERS);
+    if is_project {
+        let is_python = has(PYTHON_MARKERS)
+            || projects.last().is_some_and(|(_, python)| *python);
+        projects.push((dir.to_path_buf(), is_python));
+    }
+
+    for (entry, name) in entries.iter().zip(&names) {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+
+        if file_type.is_file() {
+            // Any file edited in a project counts as activity for it and every project around it
+            if !projects.is_empty()
+                && let Ok(modified) = entry.metadata().map(|m| m.mtime())
+            {
+                for (project, _) in projects.iter() {
+                    let newest = activity.entry(project.clone()).or_insert(0);
+                    *newest = (*newest).max(modified);
+                }
+            }
+            continue;
+        }
+        if !file_type.is_dir() {
+            continue;
+        }
+
+        if let Some((project, is_python)) = projects.last() {
+            let rule = ARTIFACT_RULES
+                .iter()
+                .find(|(artifact, markers)| artifact == name && is_project && has(markers));
+            if let Some((artifact, _)) = rule {
+                let (parts, size) = if *artifact == "target" {
+                    cargo_target_parts(&path)
+                } else {
+                    (Vec::new(), get_dir_allocated_size(&path))
+                };
+                artifacts.push(BuildArtifact {
+                    project: project.clone(),
+                    name: artifact,
+                    size,
+                    paths: vec![path],
+                    last_activity: 0,
+                    parts,
+                });
+                continue;
+            }
+            if *is_python && name == "__pycache__" {
+                let size = get_dir_allocated_size(&path);
+                match artifacts
+                    .iter_mut()
+                    .find(|a| &a.project == project && a.name == "__pycache__")
+                {
+                    Some(existing) => {
+                        existing.paths.push(path);
+                        existing.size += size;
+                    }
+                    None => artifacts.push(BuildArtifact {
+                        project: project.clone(),
+                        name: "__pycache__",
+                        paths: vec![path],
+                        size,
+                        last_activity: 0,
+                        parts: Vec::new(),
+                    }),
+                }
+                continue;
+            }
+        }
+
+        // Hidden folders and dependency trees without a project of their own
+        // hold no projects worth finding, and can be huge
+        let skip = name.starts_with('.') || name == "node_modules" || name == "target";
+        if !skip && depth < ARTIFACT_SEARCH_DEPTH {
+            find_artifacts_in(&path, depth + 1, projects, artifacts, activity);
+        }
+    }
+
+    if is_project {
+        projects.pop();
+    }
+}
+
+// What deleting the directory would free, without needing `du` per folder
+fn get_dir_allocated_size(path: &Path) -> u64 {
+    jwalk::WalkDir::new(path)
+        .skip_hidden(false)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.blocks() * 512)
+        .sum()
+}
+
+/// Splits a Cargo `target/` directory into its debug, release, incremental and
+/// doc output, and returns the size of the whole directory along with it.
+pub fn cargo_target_parts(target: &Path) -> (Vec<TargetPart>, u64) {
+    let mut parts: Vec<TargetPart> = TARGET_PARTS
+        .iter()
+        .map(|&name| TargetPart {
+            name,
+            paths: Vec::new(),
+            size: 0,
+        })
+        .collect();
+    let mut other = 0;
+
+    for entry in std::fs::read_dir(target).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if add_target_part(&mut parts, &path) {
+            continue;
+        }
+        // Cross-compiled output sits one level deeper, in target/<triple>/
+        let is_triple = ["debug", "release"].iter().any(|p| path.join(p).is_dir());
+        if !is_triple {
+            other += get_dir_allocated_size(&path);
+            continue;
+        }
+        for sub in std::fs::read_dir(&path).into_iter().flatten().flatten() {
+            if !add_target_part(&mut parts, &sub.path()) {
+                other += get_dir_allocated_size(&sub.path());
+            }
+        }
+    }
+
+    parts.retain(|p| !p.paths.is_empty());
+    let total = other + parts.iter().map(|p| p.size).sum::<u64>();
+    (parts, total)
+}
+
+// Adds `dir` to the part it holds, returning false if it holds none of them
+fn add_target_part(parts: &mut [TargetPart], dir: &Path) -> bool {
+    if !dir.is_dir() || dir.is_symlink() {
+        return false;
+    }
+    let name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    match name.as_str() {
+        "doc" => {
+            parts[3].size += get_dir_allocated_size(dir);
+            parts[3].paths.push(dir.to_path_buf());
+        }
+        "debug" | "release" => {
+            let index = if name == "debug" { 0 } else { 1 };
+            let incremental = dir.join("incremental");
+            let incremental_size = if incremental.is_dir() {
+                parts[2].paths.push(incremental.clone());
+                get_dir_allocated_size(&incremental)
+            } else {
+                0
+            };
+            parts[2].size += incremental_size;
+            parts[index].size += get_dir_allocated_size(dir).saturating_sub(incremental_size);
+            parts[index].paths.push(dir.to_path_buf());
+        }
+        _ => return false,
+    }
+    true
+}
+
+pub enum ArtifactUpdate {
+    /// A whole artifact is gone, keyed by its first path
+    Removed(PathBuf),
+    /// Parts of a Cargo `target/` directory were cleaned: the keys of those
+    /// parts, then what is left of it
+    Trimmed(PathBuf, Vec<PathBuf>, Vec<TargetPart>, u64),
+    Finished,
+}
+
+/// Removes the `selected` artifacts and target parts on a worker thread,
+/// `target/` and `node_modules/` trees can take a while to delete. Failed ones
+/// are simply not reported.
+pub fn spawn_artifact_clean(
+    artifacts: Vec<BuildArtifact>,
+    selected: HashSet<PathBuf>,
+) -> Receiver<ArtifactUpdate> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for artifact in artifacts {
+            let key = artifact.paths[0].clone();
+            if selected.contains(&key) && clean_build_artifact(&artifact) {
+                let _ = tx.send(ArtifactUpdate::Removed(key));
+                continue;
+            }
+            let cleaned: Vec<_> = artifact
+                .parts
+                .iter()
+                .filter(|part| selected.contains(&part.paths[0]) && clean_target_part(part))
+                .map(|part| part.paths[0].clone())
+                .collect();
+            if !cleaned.is_empty() {
+                let (parts, size) = cargo_target_parts(&key);
+                let _ = tx.send(ArtifactUpdate::Trimmed(key, cleaned, parts, size));
+            }
+        }
+        let _ = tx.send(ArtifactUpdate::Finished);
+    });
+    rx
+}
+
+pub fn clean_build_artifact(artifact: &BuildArtifact) -> bool {
+    remove_dirs(&artifact.paths)
+}
+
+/// Cleaning a profile takes its incremental data with it
+pub fn clean_target_part(part: &TargetPart) -> bool {
+    remove_dirs(&part.paths)
+}
+
+fn remove_dirs(paths: &[PathBuf]) -> bool {
+    paths
+        .iter()
+        .all(|path| std::fs::remove_dir_all(path).is_ok() || !path.exists())
+}
+
 pub fn get_npm_cache_size() -> u64 {
     let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
     path.push(".npm");
@@ -541,9 +856,14 @@ pub fn vacuum_journal() -> bool {
     status.map(|s| s.success()).unwrap_or(false)
 }
 
-pub fn empty_trash() -> bool {
-    let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("~/.local/share"));
-    path.push("Trash");
+/// Purges what `retention` expires from every trash, or the whole home trash
+/// when it sets no rules
+pub fn empty_trash(retention: &trash::Retention) -> bool {
+    if retention.is_set() {
+        return trash::apply_retention(retention, false).errors.is_empty();
+    }
+
+    let path = trash::home_trash();
 
     if path.exists() {
         std::fs::remove_dir_all(&path).is_ok() && std::fs::create_dir(&path).is_ok()
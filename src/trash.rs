@@ -0,0 +1,1038 @@
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs::{self, File, Metadata, OpenOptions, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use xxhash_rust::xxh3::Xxh3;
+
+// DeletionDate is local time without a zone, as the spec asks
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// How often a copy into the trash reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+const COPY_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// What a `.trashinfo` file says about the item it belongs to
+#[derive(Clone, Debug)]
+pub struct TrashInfo {
+    /// Where the item came from, absolute
+    pub path: PathBuf,
+    pub deletion_date: Option<NaiveDateTime>,
+}
+
+/// An item in one of the trashes, as listed by `list_entries`
+#[derive(Clone, Debug)]
+pub struct TrashEntry {
+    /// Its name in the trash's files folder, the info file adds ".trashinfo"
+    pub name: OsString,
+    pub trash_dir: PathBuf,
+    pub topdir: PathBuf,
+    pub original_path: PathBuf,
+    pub deletion_date: Option<NaiveDateTime>,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+impl TrashEntry {
+    pub fn file_path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    pub fn info_path(&self) -> PathBuf {
+        let mut info_name = self.name.clone();
+        info_name.push(".trashinfo");
+        self.trash_dir.join("info").join(info_name)
+    }
+}
+
+/// How much the trash may hold, from the `[trash]` section of the config
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Retention {
+    pub max_age_days: Option<u32>,
+    pub max_bytes: Option<u64>,
+}
+
+impl Retention {
+    pub fn is_set(&self) -> bool {
+        self.max_age_days.is_some() || self.max_bytes.is_some()
+    }
+
+    /// e.g. "older than 30 days, beyond 10.0 GB"
+    pub fn describe(&self) -> String {
+        let mut rules = Vec::new();
+        if let Some(days) = self.max_age_days {
+            rules.push(format!("older than {} days", days));
+        }
+        if let Some(bytes) = self.max_bytes {
+            rules.push(format!("beyond {}", crate::system::format_bytes(bytes)));
+        }
+        rules.join(", ")
+    }
+}
+
+/// What applying a `Retention` removed, or would remove on a dry run
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub purged: Vec<TrashEntry>,
+    pub errors: Vec<String>,
+}
+
+/// How far a copy into the trash has got. Every byte is counted twice, once
+/// when it is copied and once when the copy is read back to check it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoveProgress {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// $XDG_DATA_HOME/Trash, the trash for everything in the home directory
+pub fn home_trash() -> PathBuf {
+    let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("~/.local/share"));
+    path.push("Trash");
+    path
+}
+
+/// Whether the current user can trash `path` at all, rather than only
+/// delete it with root rights
+pub fn can_trash(path: &Path) -> bool {
+    if dirs::home_dir().is_some_and(|home| path.starts_with(home)) {
+        return true;
+    }
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    let Ok(parent) = c_path(parent) else {
+        return false;
+    };
+    unsafe { libc::access(parent.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// The trash `path` belongs in and the top directory its info file paths are
+/// relative to: the home trash for anything on the same filesystem as it,
+/// otherwise the trash at the top of the volume `path` is on.
+pub fn trash_dir_for(path: &Path) -> Result<(PathBuf, PathBuf), String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("{} can't be trashed", path.display()))?;
+    let device = fs::metadata(parent).map_err(|e| e.to_string())?.dev();
+
+    let home_trash = home_trash();
+    let home_device = home_trash
+        .ancestors()
+        .find_map(|p| fs::metadata(p).ok())
+        .map(|m| m.dev());
+    if home_device == Some(device) {
+        return Ok((home_trash, PathBuf::from("/")));
+    }
+
+    let topdir = parent
+        .ancestors()
+        .take_while(|p| fs::metadata(p).is_ok_and(|m| m.dev() == device))
+        .last()
+        .unwrap_or(parent)
+        .to_path_buf();
+    let uid = unsafe { libc::getuid() };
+
+    // $topdir/.Trash is set up by an admin for all users, sticky like /tmp.
+    // A symlink there could point anywhere, so it doesn't count.
+    let shared = topdir.join(".Trash");
+    if let Ok(meta) = fs::symlink_metadata(&shared)
+        && meta.is_dir()
+        && meta.mode() & libc::S_ISVTX != 0
+    {
+        let own = shared.join(uid.to_string());
+        if private_dir(&own, uid) {
+            return Ok((own, topdir));
+        }
+    }
+
+    let own = topdir.join(format!(".Trash-{}", uid));
+    if private_dir(&own, uid) {
+        return Ok((own, topdir));
+    }
+    Err(format!("No trash can be used on the volume at {}", topdir.display()))
+}
+
+// Creates `dir` readable only by us if it's missing, and checks that it's a
+// real folder of ours, not a link or somebody else's
+fn private_dir(dir: &Path, uid: u32) -> bool {
+    let _ = fs::DirBuilder::new().mode(0o700).create(dir);
+    fs::symlink_metadata(dir).is_ok_and(|m| m.is_dir() && m.uid() == uid)
+}
+
+/// Trashes `original_path` in the trash it belongs in, or copies it to the home
+/// trash when its volume has none we can use. Returns the trashed item's new
+/// path, the info file's path and the trash's top directory.
+pub fn trash(
+    original_path: &Path,
+    report: &mut dyn FnMut(MoveProgress),
+) -> Result<(PathBuf, PathBuf, PathBuf), String> {
+    let (trash_dir, topdir) =
+        trash_dir_for(original_path).unwrap_or_else(|_| (home_trash(), PathBuf::from("/")));
+    let (file_path, info_path) = trash_into(&trash_dir, &topdir, original_path, report)?;
+    Ok((file_path, info_path, topdir))
+}
+
+/// Moves `original_path` into `trash_dir` and writes its info file, copying it
+/// over if the trash is on another filesystem. Returns the trashed item's new
+/// path and the info file's path.
+pub fn trash_into(
+    trash_dir: &Path,
+    topdir: &Path,
+    original_path: &Path,
+    report: &mut dyn FnMut(MoveProgress),
+) -> Result<(PathBuf, PathBuf), String> {
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    fs::create_dir_all(&files_dir).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&info_dir).map_err(|e| e.to_string())?;
+
+    let file_name = original_path
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", original_path.display()))?;
+    // Per-volume trashes store paths relative to the top of the volume, so
+    // they still work when it's mounted somewhere else
+    let info_path = match original_path.strip_prefix(topdir) {
+        Ok(relative) if topdir != Path::new("/") => relative,
+        _ => original_path,
+    };
+    let content = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(info_path),
+        chrono::Local::now().format(DELETION_DATE_FORMAT)
+    );
+
+    let (trash_file_path, trash_info_path) = reserve_name(&files_dir, &info_dir, file_name, &content)
+        .map_err(|e| format!("Failed to write trash info: {}", e))?;
+
+    match fs::rename(original_path, &trash_file_path) {
+        Ok(()) => {}
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            let copied = match copy_verified(original_path, &trash_file_path, report) {
+                Ok(copied) => copied,
+                Err(e) => {
+                    let _ = fs::remove_file(&trash_info_path);
+                    return Err(format!("Failed to copy to trash: {}", e));
+                }
+            };
+            // Only now that a complete copy is in the trash
+            remove_copied(&copied).map_err(|e| {
+                format!(
+                    "Copied to the trash, but {} was not removed completely: {}",
+                    original_path.display(),
+                    e
+                )
+            })?;
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&trash_info_path);
+            return Err(format!("Failed to move to trash: {}", e));
+        }
+    }
+    Ok((trash_file_path, trash_info_path))
+}
+
+// What was copied from the source of a move across filesystems, so only that
+// gets removed afterwards
+struct CopiedEntry {
+    path: PathBuf,
+    // Inode, size and modification time of anything but a folder when it was
+    // copied. A folder is removed once empty, so anything new in it stays.
+    identity: Option<(u64, u64, i64, i64)>,
+}
+
+fn identity(meta: &Metadata) -> Option<(u64, u64, i64, i64)> {
+    (!meta.is_dir()).then(|| (meta.ino(), meta.len(), meta.mtime(), meta.mtime_nsec()))
+}
+
+// Removes the source side of a verified copy, deepest entries first. Entries
+// that changed since they were copied and folders that gained entries are
+// left where they are.
+fn remove_copied(copied: &[CopiedEntry]) -> io::Result<()> {
+    let mut kept = 0;
+    let mut first_error = None;
+    for entry in copied.iter().rev() {
+        let result = match entry.identity {
+            None => fs::remove_dir(&entry.path),
+            Some(copied_as) => match fs::symlink_metadata(&entry.path) {
+                Ok(meta) if identity(&meta) == Some(copied_as) => fs::remove_file(&entry.path),
+                Ok(_) => Err(io::Error::other("changed after it was copied")),
+                Err(e) => Err(e),
+            },
+        };
+        if let Err(e) = result {
+            kept += 1;
+            first_error.get_or_insert_with(|| format!("{}: {}", entry.path.display(), e));
+        }
+    }
+    match first_error {
+        None => Ok(()),
+        Some(e) => Err(io::Error::other(format!("{} item(s) left in place, {}", kept, e))),
+    }
+}
+
+// Clears away a copy that failed halfway. It is all ours, so folders copied
+// without write access get it back first.
+fn remove_partial_copy(path: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return fs::remove_file(path);
+    }
+    fs::set_permissions(path, Permissions::from_mode(0o700))?;
+    for entry in fs::read_dir(path)? {
+        remove_partial_copy(&entry?.path())?;
+    }
+    fs::remove_dir(path)
+}
+
+fn remove_all(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+struct Copier<'a> {
+    progress: MoveProgress,
+    last_update: Instant,
+    report: &'a mut dyn FnMut(MoveProgress),
+    buffer: Vec<u8>,
+    // Files with several names inside the tree, so the copy links them the same way
+    linked: HashMap<(u64, u64), PathBuf>,
+    copied: Vec<CopiedEntry>,
+    target: PathBuf,
+    // Whether this copy created `target`, only then may a failed copy clear it away
+    created_target: bool,
+}
+
+impl Copier<'_> {
+    fn created(&mut self, path: &Path) {
+        if path == self.target {
+            self.created_target = true;
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.progress.bytes_done += bytes;
+        if self.last_update.elapsed() >= PROGRESS_INTERVAL {
+            self.last_update = Instant::now();
+            (self.report)(self.progress);
+        }
+    }
+}
+
+// Copies `source` to `target` with everything the trash should keep about it:
+// contents, permissions, ownership where allowed, timestamps, symlinks and
+// extended attributes. Every file is read back and checked against what was
+// read from the source. Returns what was copied, parents before children.
+// When it fails, whatever it created is removed again and nothing else.
+fn copy_verified(
+    source: &Path,
+    target: &Path,
+    report: &mut dyn FnMut(MoveProgress),
+) -> io::Result<Vec<CopiedEntry>> {
+    let mut copier = Copier {
+        progress: MoveProgress {
+            bytes_done: 0,
+            bytes_total: tree_bytes(source)? * 2,
+        },
+        last_update: Instant::now(),
+        report,
+        buffer: vec![0; COPY_BUFFER_BYTES],
+        linked: HashMap::new(),
+        copied: Vec::new(),
+        target: target.to_path_buf(),
+        created_target: false,
+    };
+    (copier.report)(copier.progress);
+    if let Err(e) = copy_entry(source, target, &mut copier) {
+        if copier.created_target {
+            let _ = remove_partial_copy(target);
+        }
+        return Err(e);
+    }
+    (copier.report)(copier.progress);
+    Ok(copier.copied)
+}
+
+fn tree_bytes(path: &Path) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(if meta.is_file() { meta.len() } else { 0 });
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += tree_bytes(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+fn copy_entry(source: &Path, target: &Path, copier: &mut Copier) -> io::Result<()> {
+    let meta = fs::symlink_metadata(source)?;
+    let file_type = meta.file_type();
+
+    if file_type.is_dir() {
+        fs::DirBuilder::new().mode(0o700).create(target)?;
+        copier.created(target);
+        copier.copied.push(CopiedEntry {
+            path: source.to_path_buf(),
+            identity: None,
+        });
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &target.join(entry.file_name()), copier)?;
+        }
+    } else if file_type.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+        copier.created(target);
+    } else if file_type.is_file() {
+        if meta.nlink() > 1
+            && let Some(first) = copier.linked.get(&(meta.dev(), meta.ino()))
+        {
+            fs::hard_link(first, target)?;
+            copier.created(target);
+            copier.copied.push(CopiedEntry {
+                path: source.to_path_buf(),
+                identity: identity(&meta),
+            });
+            return Ok(());
+        }
+        copy_file(source, target, &meta, copier)?;
+        if meta.nlink() > 1 {
+            copier.linked.insert((meta.dev(), meta.ino()), target.to_path_buf());
+        }
+    } else {
+        // Pipes, sockets and device nodes are just recreated
+        let path = c_path(target)?;
+        if unsafe { libc::mknod(path.as_ptr(), meta.mode(), meta.rdev()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        copier.created(target);
+    }
+    copy_metadata(source, target, &meta)?;
+    if !file_type.is_dir() {
+        copier.copied.push(CopiedEntry {
+            path: source.to_path_buf(),
+            identity: identity(&meta),
+        });
+    }
+    Ok(())
+}
+
+fn copy_file(source: &Path, target: &Path, meta: &Metadata, copier: &mut Copier) -> io::Result<()> {
+    let mut input = File::open(source)?;
+    let mut output = OpenOptions::new().write(true).create_new(true).mode(0o600).open(target)?;
+    copier.created(target);
+    let mut hasher = Xxh3::new();
+    loop {
+        let read = match input.read(&mut copier.buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&copier.buffer[..read])?;
+        hasher.update(&copier.buffer[..read]);
+        copier.advance(read as u64);
+    }
+    output.sync_all()?;
+
+    // A file written to while it was copied could leave a torn copy behind
+    let after = input.metadata()?;
+    if after.len() != meta.len() || after.mtime() != meta.mtime() || after.mtime_nsec() != meta.mtime_nsec() {
+        return Err(io::Error::other(format!("{} changed while it was copied", source.display())));
+    }
+
+    let mut written = File::open(target)?;
+    let mut check = Xxh3::new();
+    loop {
+        let read = match written.read(&mut copier.buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        check.update(&copier.buffer[..read]);
+        copier.advance(read as u64);
+    }
+    if check.digest128() != hasher.digest128() {
+        return Err(io::Error::other(format!("The copy of {} doesn't match it", source.display())));
+    }
+    Ok(())
+}
+
+// Attributes first, they can't be written once the mode drops write access.
+// Times last, since setting anything else touches them.
+fn copy_metadata(source: &Path, target: &Path, meta: &Metadata) -> io::Result<()> {
+    copy_xattrs(source, target)?;
+    let path = c_path(target)?;
+    // Only root can give files away, for everyone else the copy is ours anyway
+    unsafe { libc::lchown(path.as_ptr(), meta.uid(), meta.gid()) };
+    if !meta.file_type().is_symlink() {
+        fs::set_permissions(target, Permissions::from_mode(meta.mode() & 0o7777))?;
+    }
+    let times = [
+        libc::timespec {
+            tv_sec: meta.atime(),
+            tv_nsec: meta.atime_nsec(),
+        },
+        libc::timespec {
+            tv_sec: meta.mtime(),
+            tv_nsec: meta.mtime_nsec(),
+        },
+    ];
+    if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn copy_xattrs(source: &Path, target: &Path) -> io::Result<()> {
+    let source = c_path(source)?;
+    let target = c_path(target)?;
+
+    let names = match xattr_buffer(|buf, len| unsafe { libc::llistxattr(source.as_ptr(), buf, len) }) {
+        Ok(names) => names,
+        // Nothing to copy from a filesystem without extended attributes
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for name in names.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+        let name = CString::new(name).map_err(io::Error::other)?;
+        let value = xattr_buffer(|buf, len| unsafe {
+            libc::lgetxattr(source.as_ptr(), name.as_ptr(), buf as *mut libc::c_void, len)
+        })?;
+        let set = unsafe {
+            libc::lsetxattr(
+                target.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if set != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// Calls an xattr function first to learn the size and then to fill a buffer of
+// that size, again if the value grew in between
+fn xattr_buffer(call: impl Fn(*mut libc::c_char, usize) -> libc::ssize_t) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        let filled = call(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len());
+        if filled >= 0 {
+            buffer.truncate(filled as usize);
+            return Ok(buffer);
+        }
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::ERANGE) {
+            return Err(e);
+        }
+    }
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+}
+
+// Creating the info file with O_EXCL is what claims a name, so two programs
+// trashing a file of the same name at once never end up sharing one
+fn reserve_name(
+    files_dir: &Path,
+    info_dir: &Path,
+    file_name: &OsStr,
+    content: &str,
+) -> io::Result<(PathBuf, PathBuf)> {
+    for n in 1.. {
+        let name = numbered_name(file_name, n);
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        let info_path = info_dir.join(&info_name);
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        // Left behind without an info file by some other program, don't overwrite it
+        let file_path = files_dir.join(&name);
+        if file_path.symlink_metadata().is_ok() {
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+        if let Err(e) = file.write_all(content.as_bytes()).and_then(|_| file.sync_all()) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+        return Ok((file_path, info_path));
+    }
+    unreachable!()
+}
+
+// name, name.2, name.3 ... with the number before the extension like GIO
+// does, so "notes.txt" becomes "notes.2.txt"
+fn numbered_name(file_name: &OsStr, n: u32) -> OsString {
+    if n == 1 {
+        return file_name.to_os_string();
+    }
+    let bytes = file_name.as_bytes();
+    // A leading dot is part of the name, not an extension
+    let stem_len = bytes
+        .iter()
+        .skip(1)
+        .position(|&b| b == b'.')
+        .map_or(bytes.len(), |dot| dot + 1);
+    let mut name = bytes[..stem_len].to_vec();
+    name.extend_from_slice(format!(".{}", n).as_bytes());
+    name.extend_from_slice(&bytes[stem_len..]);
+    OsString::from_vec(name)
+}
+
+/// Percent-encodes every byte of `path` that isn't allowed as is in a URI path
+pub fn encode_path(path: &Path) -> String {
+    let mut out = String::new();
+    for &b in path.as_os_str().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~!$&'()*+,;=:@".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Undoes `encode_path`, None for broken escapes
+pub fn decode_path(encoded: &str) -> Option<PathBuf> {
+    let bytes = encoded.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(out)))
+}
+
+/// Reads a `.trashinfo` file. Relative paths are taken as relative to
+/// `topdir`, which is how per-mount trashes store them.
+pub fn read_info(info_path: &Path, topdir: &Path) -> Result<TrashInfo, String> {
+    let content = fs::read_to_string(info_path).map_err(|e| e.to_string())?;
+    parse_info(&content, topdir).ok_or_else(|| format!("{} is not a valid trash info file", info_path.display()))
+}
+
+pub fn parse_info(content: &str, topdir: &Path) -> Option<TrashInfo> {
+    let mut in_group = false;
+    let mut path = None;
+    let mut deletion_date = None;
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with('[') {
+            in_group = line == "[Trash Info]";
+            continue;
+        }
+        if !in_group {
+            continue;
+        }
+        // The first of repeated keys counts
+        if let Some(value) = line.strip_prefix("Path=")
+            && path.is_none()
+        {
+            // Older Diskord versions wrote the path without escaping it
+            path = decode_path(value).or_else(|| Some(PathBuf::from(value)));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=")
+            && deletion_date.is_none()
+        {
+            deletion_date = NaiveDateTime::parse_from_str(value, DELETION_DATE_FORMAT).ok();
+        }
+    }
+    let path = path.filter(|p| !p.as_os_str().is_empty())?;
+    Some(TrashInfo {
+        path: topdir.join(path),
+        deletion_date,
+    })
+}
+
+/// Every trash of the current user that exists: the home trash and those at
+/// the top of mounted volumes, each with the top directory it belongs to
+pub fn trash_dirs() -> Vec<(PathBuf, PathBuf)> {
+    let home_trash = home_trash();
+    let mut dirs = vec![(home_trash.clone(), PathBuf::from("/"))];
+    let uid = unsafe { libc::getuid() };
+
+    for topdir in mount_points() {
+        let shared = topdir.join(".Trash");
+        let shared_ok = fs::symlink_metadata(&shared)
+            .is_ok_and(|m| m.is_dir() && m.mode() & libc::S_ISVTX != 0);
+        let candidates = [
+            shared_ok.then(|| shared.join(uid.to_string())),
+            Some(topdir.join(format!(".Trash-{}", uid))),
+        ];
+        for dir in candidates.into_iter().flatten() {
+            if dir != home_trash
+                && fs::symlink_metadata(&dir).is_ok_and(|m| m.is_dir() && m.uid() == uid)
+            {
+                dirs.push((dir, topdir.clone()));
+            }
+        }
+    }
+    dirs
+}
+
+// Mount points from /proc/self/mounts, where spaces and the like are octal escapes
+fn mount_points() -> Vec<PathBuf> {
+    let Ok(mounts) = fs::read(Path::new("/proc/self/mounts")) else {
+        return Vec::new();
+    };
+    let mut points: Vec<PathBuf> = mounts
+        .split(|&b| b == b'\n')
+        .filter_map(|line| line.split(|&b| b == b' ').nth(1))
+        .map(|field| {
+            let mut out = Vec::with_capacity(field.len());
+            let mut i = 0;
+            while i < field.len() {
+                let octal = field.get(i + 1..i + 4).and_then(|digits| {
+                    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok()
+                });
+                match octal {
+                    Some(byte) if field[i] == b'\\' => {
+                        out.push(byte);
+                        i += 4;
+                    }
+                    _ => {
+                        out.push(field[i]);
+                        i += 1;
+                    }
+                }
+            }
+            PathBuf::from(OsString::from_vec(out))
+        })
+        .collect();
+    points.sort();
+    points.dedup();
+    points
+}
+
+/// Everything in `trash_dirs`, newest first. Info files whose item is missing
+/// and items without a readable info file are left out.
+pub fn list_entries() -> Vec<TrashEntry> {
+    let mut entries = Vec::new();
+    for (trash_dir, topdir) in trash_dirs() {
+        let Ok(infos) = fs::read_dir(trash_dir.join("info")) else {
+            continue;
+        };
+        for info in infos.flatten() {
+            let info_name = info.file_name();
+            let Some(name) = info_name.as_bytes().strip_suffix(b".trashinfo") else {
+                continue;
+            };
+            let name = OsStr::from_bytes(name).to_os_string();
+            let Ok(meta) = fs::symlink_metadata(trash_dir.join("files").join(&name)) else {
+                continue;
+            };
+            let Ok(parsed) = read_info(&info.path(), &topdir) else {
+                continue;
+            };
+            let size = if meta.is_dir() {
+                tree_bytes(&trash_dir.join("files").join(&name)).unwrap_or(0)
+            } else {
+                meta.len()
+            };
+            entries.push(TrashEntry {
+                name,
+                trash_dir: trash_dir.clone(),
+                topdir: topdir.clone(),
+                original_path: parsed.path,
+                deletion_date: parsed.deletion_date,
+                size,
+                is_dir: meta.is_dir(),
+            });
+        }
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.deletion_date));
+    entries
+}
+
+/// Indices of the `entries` that `retention` expires: everything past the age
+/// limit, then the oldest of the rest until what's left fits the size limit.
+/// Items without a deletion date count as the oldest for the size limit, but
+/// are never too old.
+pub fn expired(entries: &[TrashEntry], retention: &Retention, now: NaiveDateTime) -> Vec<usize> {
+    let mut oldest_first: Vec<usize> = (0..entries.len()).collect();
+    oldest_first.sort_by_key(|&i| entries[i].deletion_date);
+
+    let mut expired = Vec::new();
+    let mut remaining: u64 = entries.iter().map(|e| e.size).sum();
+    if let Some(days) = retention.max_age_days {
+        let cutoff = now - chrono::Duration::days(days as i64);
+        oldest_first.retain(|&i| {
+            let too_old = entries[i].deletion_date.is_some_and(|d| d < cutoff);
+            if too_old {
+                expired.push(i);
+                remaining -= entries[i].size;
+            }
+            !too_old
+        });
+    }
+    if let Some(max_bytes) = retention.max_bytes {
+        for i in oldest_first {
+            if remaining <= max_bytes {
+                break;
+            }
+            expired.push(i);
+            remaining -= entries[i].size;
+        }
+    }
+    expired
+}
+
+/// Deletes whatever `retention` expires from every trash, or only reports it
+/// when `dry_run` is set
+pub fn apply_retention(retention: &Retention, dry_run: bool) -> PurgeReport {
+    let mut report = PurgeReport::default();
+    if !retention.is_set() {
+        return report;
+    }
+    let mut entries = list_entries();
+    let now = chrono::Local::now().naive_local();
+    let mut expired = expired(&entries, retention, now);
+    // Taken out back to front so the indices stay valid
+    expired.sort_unstable_by(|a, b| b.cmp(a));
+    for i in expired {
+        let entry = entries.swap_remove(i);
+        if !dry_run && let Err(e) = delete_entry(&entry) {
+            report.errors.push(format!("{}: {}", entry.original_path.display(), e));
+            continue;
+        }
+        report.purged.push(entry);
+    }
+    report.purged.sort_by_key(|e| e.deletion_date);
+    report
+}
+
+/// Removes an item from the trash for good, the info file last so the item
+/// never lingers in the trash without one
+pub fn delete_entry(entry: &TrashEntry) -> Result<(), String> {
+    let file_path = entry.file_path();
+    if fs::symlink_metadata(&file_path).is_ok() {
+        remove_all(&file_path).map_err(|e| e.to_string())?;
+    }
+    fs::remove_file(entry.info_path()).map_err(|e| e.to_string())
+}
+
+/// Moves a trashed item back to where its info file says it came from and
+/// removes the info file. Never overwrites anything that is there now.
+/// Items that came from another filesystem are copied back the same careful
+/// way they were copied in.
+pub fn restore(
+    trash_file_path: &Path,
+    trash_info_path: &Path,
+    topdir: &Path,
+    report: &mut dyn FnMut(MoveProgress),
+) -> Result<PathBuf, String> {
+    let info = read_info(trash_info_path, topdir)?;
+    if let Some(parent) = info.path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let exists = |e: io::Error| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            format!("{} already exists", info.path.display())
+        } else {
+            e.to_string()
+        }
+    };
+    match rename_no_replace(trash_file_path, &info.path) {
+        Ok(()) => {}
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            // The copy never replaces anything either, every entry is created exclusively
+            let copied = match copy_verified(trash_file_path, &info.path, report) {
+                Ok(copied) => copied,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    return Err(format!("{} already exists", info.path.display()));
+                }
+                Err(e) => return Err(format!("Failed to copy back: {}", e)),
+            };
+            // Folders copied without write access keep the rest from being removed
+            for entry in copied.iter().filter(|e| e.identity.is_none()) {
+                let _ = fs::set_permissions(&entry.path, Permissions::from_mode(0o700));
+            }
+            if let Err(e) = remove_copied(&copied) {
+                // Restored, only a leftover stays in the trash
+                return Err(format!(
+                    "Restored {}, but the copy in the trash was not removed completely: {}",
+                    info.path.display(),
+                    e
+                ));
+            }
+        }
+        Err(e) => return Err(exists(e)),
+    }
+    let _ = fs::remove_file(trash_info_path);
+    Ok(info.path)
+}
+
+// A rename that fails instead of replacing `to`, even when something shows up
+// there at the last moment
+fn rename_no_replace(from: &Path, to: &Path) -> io::Result<()> {
+    let (from_c, to_c) = (c_path(from)?, c_path(to)?);
+    let renamed = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            from_c.as_ptr(),
+            libc::AT_FDCWD,
+            to_c.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if renamed == 0 {
+        return Ok(());
+    }
+    let e = io::Error::last_os_error();
+    if !matches!(e.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS)) {
+        return Err(e);
+    }
+
+    // Filesystems without RENAME_NOREPLACE. A new link fails if the name is
+    // taken, and a folder can only be renamed over the empty one made for it.
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::create_dir(to)?;
+        fs::rename(from, to).inspect_err(|_| {
+            let _ = fs::remove_dir(to);
+        })
+    } else {
+        fs::hard_link(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_reserved_and_non_utf8_bytes() {
+        let path = PathBuf::from(OsString::from_vec(b"/home/u/a b%c#\xff.txt".to_vec()));
+        assert_eq!(encode_path(&path), "/home/u/a%20b%25c%23%FF.txt");
+        assert_eq!(encode_path(Path::new("/srv/x-y_z~(1)")), "/srv/x-y_z~(1)");
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let path = PathBuf::from(OsString::from_vec(b"/tmp/\n\t%20 \xc3\xa9\xfe".to_vec()));
+        assert_eq!(decode_path(&encode_path(&path)), Some(path));
+        assert_eq!(decode_path("/a%2fb"), Some(PathBuf::from("/a/b")));
+    }
+
+    #[test]
+    fn rejects_broken_escapes() {
+        assert_eq!(decode_path("/a%2"), None);
+        assert_eq!(decode_path("/a%zz"), None);
+        assert_eq!(decode_path("/a%"), None);
+    }
+
+    #[test]
+    fn parses_info_files() {
+        let info = parse_info(
+            "[Trash Info]\r\nPath=/home/u/my%20file\r\nDeletionDate=2024-02-03T04:05:06\r\n",
+            Path::new("/"),
+        )
+        .unwrap();
+        assert_eq!(info.path, PathBuf::from("/home/u/my file"));
+        assert_eq!(
+            info.deletion_date,
+            NaiveDateTime::parse_from_str("2024-02-03T04:05:06", DELETION_DATE_FORMAT).ok()
+        );
+    }
+
+    #[test]
+    fn resolves_relative_paths_against_the_topdir() {
+        let info = parse_info("[Trash Info]\nPath=photos/a.jpg\n", Path::new("/mnt/usb")).unwrap();
+        assert_eq!(info.path, PathBuf::from("/mnt/usb/photos/a.jpg"));
+        assert!(info.deletion_date.is_none());
+    }
+
+    #[test]
+    fn only_reads_the_trash_info_group() {
+        let content = "[Other]\nPath=/wrong\n[Trash Info]\nPath=/right\nPath=/later\n";
+        assert_eq!(parse_info(content, Path::new("/")).unwrap().path, PathBuf::from("/right"));
+        assert!(parse_info("[Other]\nPath=/wrong\n", Path::new("/")).is_none());
+        assert!(parse_info("[Trash Info]\nPath=\n", Path::new("/")).is_none());
+    }
+
+    #[test]
+    fn restore_never_overwrites() {
+        let dir = std::env::temp_dir().join(format!("diskord-restore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let trash_dir = dir.join("Trash");
+        fs::create_dir_all(dir.join("home")).unwrap();
+        let original = dir.join("home/a b.txt");
+        fs::write(&original, "old").unwrap();
+
+        let (file, info) = trash_into(&trash_dir, Path::new("/"), &original, &mut |_| {}).unwrap();
+        fs::write(&original, "new").unwrap();
+        assert!(restore(&file, &info, Path::new("/"), &mut |_| {}).is_err());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "new");
+        assert!(file.exists() && info.exists());
+
+        fs::remove_file(&original).unwrap();
+        assert_eq!(restore(&file, &info, Path::new("/"), &mut |_| {}).unwrap(), original);
+        assert_eq!(fs::read_to_string(&original).unwrap(), "old");
+        assert!(!info.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_copies_leave_existing_targets_alone() {
+        let dir = std::env::temp_dir().join(format!("diskord-copy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("source")).unwrap();
+        fs::write(dir.join("source/file"), "copied").unwrap();
+        fs::write(dir.join("target"), "new").unwrap();
+
+        // The trash item went missing, or the original location was taken in the meantime
+        assert!(copy_verified(&dir.join("missing"), &dir.join("target"), &mut |_| {}).is_err());
+        assert!(copy_verified(&dir.join("source"), &dir.join("target"), &mut |_| {}).is_err());
+        assert_eq!(fs::read_to_string(dir.join("target")).unwrap(), "new");
+
+        let copied = copy_verified(&dir.join("source"), &dir.join("copy"), &mut |_| {}).unwrap();
+        assert_eq!(copied.len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("copy/file")).unwrap(), "copied");
+
+        let (file, info) = trash_into(&dir.join("Trash"), Path::new("/"), &dir.join("target"), &mut |_| {}).unwrap();
+        fs::remove_file(&file).unwrap();
+        fs::write(dir.join("target"), "newer").unwrap();
+        assert!(restore(&file, &info, Path::new("/"), &mut |_| {}).is_err());
+        assert_eq!(fs::read_to_string(dir.join("target")).unwrap(), "newer");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn numbers_names_before_the_extension() {
+        let name = |n, i| numbered_name(OsStr::new(n), i).into_string().unwrap();
+        assert_eq!(name("notes.txt", 1), "notes.txt");
+        assert_eq!(name("notes.txt", 2), "notes.2.txt");
+        assert_eq!(name("archive.tar.gz", 3), "archive.3.tar.gz");
+        assert_eq!(name("README", 2), "README.2");
+        assert_eq!(name(".bashrc", 2), ".bashrc.2");
+        assert_eq!(name(".config.toml", 2), ".config.2.toml");
+    }
+}
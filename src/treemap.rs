@@ -0,0 +1,216 @@
+use ratatui::layout::Rect;
+
+// Terminal cells are about twice as tall as they are wide, squares are laid
+// out in a space stretched by this much vertically
+const CELL_ASPECT: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Area {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Squarified treemap (Bruls, Huizing and van Wijk) of `sizes`, which have to
+/// be sorted biggest first. Returns one rectangle per size, of zero width where
+/// an item is too small to get a cell of its own.
+pub fn layout(sizes: &[u64], area: Rect) -> Vec<Rect> {
+    let mut rects = vec![Rect::default(); sizes.len()];
+    let count = sizes.iter().take_while(|&&s| s > 0).count();
+    let total: u64 = sizes[..count].iter().sum();
+    if count == 0 || area.area() == 0 {
+        return rects;
+    }
+
+    let mut free = Area {
+        x: 0.0,
+        y: 0.0,
+        w: area.width as f64,
+        h: area.height as f64 * CELL_ASPECT,
+    };
+    let scale = free.w * free.h / total as f64;
+    let areas: Vec<f64> = sizes[..count].iter().map(|&s| s as f64 * scale).collect();
+
+    let mut placed = vec![Area::default(); count];
+    let mut start = 0;
+    while start < count {
+        // Keep adding items to the row for as long as that makes its worst aspect ratio better
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < count && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+
+        let row_area: f64 = areas[start..end].iter().sum();
+        if free.w >= free.h {
+            // A column along the left edge
+            let width = row_area / free.h;
+            let mut y = free.y;
+            for i in start..end {
+                let height = areas[i] / width;
+                placed[i] = Area { x: free.x, y, w: width, h: height };
+                y += height;
+            }
+            free.x += width;
+            free.w -= width;
+        } else {
+            // A row along the top edge
+            let height = row_area / free.w;
+            let mut x = free.x;
+            for i in start..end {
+                let width = areas[i] / height;
+                placed[i] = Area { x, y: free.y, w: width, h: height };
+                x += width;
+            }
+            free.y += height;
+            free.h -= height;
+        }
+        start = end;
+    }
+
+    // Snap to cells by rounding the edges, so neighbours never overlap or leave gaps
+    for (rect, a) in rects.iter_mut().zip(&placed) {
+        let x0 = a.x.round() as u16;
+        let x1 = (a.x + a.w).round() as u16;
+        let y0 = (a.y / CELL_ASPECT).round() as u16;
+        let y1 = ((a.y + a.h) / CELL_ASPECT).round() as u16;
+        if x1 > x0 && y1 > y0 {
+            *rect = Rect::new(area.x + x0, area.y + y0, x1 - x0, y1 - y0);
+        }
+    }
+    rects
+}
+
+// How far from square the least square rectangle of a row along `side` would be
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side2 = side * side;
+    let sum2 = sum * sum;
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// The rectangle next to `rects[from]` in `direction`, preferring ones that
+/// line up with it over ones that are merely closer.
+pub fn neighbour(rects: &[Rect], from: usize, direction: Direction) -> Option<usize> {
+    // From an item too small to show, any key lands on the biggest one
+    let Some(current) = rects.get(from).filter(|r| r.area() > 0) else {
+        return rects.iter().position(|r| r.area() > 0);
+    };
+    let center = |r: &Rect| (r.x as i32 * 2 + r.width as i32, r.y as i32 * 2 + r.height as i32);
+    let (cx, cy) = center(current);
+
+    rects
+        .iter()
+        .enumerate()
+        .filter(|&(i, r)| i != from && r.area() > 0)
+        .filter_map(|(i, r)| {
+            let (x, y) = center(r);
+            // Distance along the direction and how far off to the side, both in half cells
+            let (ahead, aside, overlaps) = match direction {
+                Direction::Right if r.x >= current.right() => {
+                    (x - cx, y - cy, r.y < current.bottom() && r.bottom() > current.y)
+                }
+                Direction::Left if r.right() <= current.x => {
+                    (cx - x, y - cy, r.y < current.bottom() && r.bottom() > current.y)
+                }
+                Direction::Down if r.y >= current.bottom() => {
+                    (y - cy, x - cx, r.x < current.right() && r.right() > current.x)
+                }
+                Direction::Up if r.bottom() <= current.y => {
+                    (cy - y, x - cx, r.x < current.right() && r.right() > current.x)
+                }
+                _ => return None,
+            };
+            Some((i, (!overlaps, ahead + aside.abs() * 2)))
+        })
+        .min_by_key(|&(_, key)| key)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // How many rectangles cover each cell of `area`
+    fn coverage(rects: &[Rect], area: Rect) -> Vec<u32> {
+        let mut cells = vec![0; area.area() as usize];
+        for r in rects {
+            assert!(r.area() == 0 || area.union(*r) == area, "{r:?} outside of {area:?}");
+            for y in r.top()..r.bottom() {
+                for x in r.left()..r.right() {
+                    cells[((y - area.y) * area.width + x - area.x) as usize] += 1;
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn tiles_the_area_without_overlaps() {
+        let cases: [&[u64]; 4] = [
+            &[100],
+            &[60, 30, 10],
+            &[500, 400, 300, 200, 100, 50, 20, 10, 5, 1],
+            &[7, 7, 7, 7, 7, 7],
+        ];
+        for sizes in cases {
+            for area in [Rect::new(0, 0, 80, 24), Rect::new(3, 2, 17, 40), Rect::new(10, 5, 120, 9)] {
+                let rects = layout(sizes, area);
+                assert_eq!(rects.len(), sizes.len());
+                assert!(coverage(&rects, area).iter().all(|&n| n == 1), "{sizes:?} in {area:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn items_too_small_or_empty_get_no_cell() {
+        let area = Rect::new(0, 0, 20, 10);
+        let rects = layout(&[1_000_000, 1, 0], area);
+        assert_eq!(rects[0], area);
+        assert_eq!(rects[1].area(), 0);
+        assert_eq!(rects[2].area(), 0);
+        assert!(layout(&[0, 0], area).iter().all(|r| r.area() == 0));
+        assert!(layout(&[5], Rect::new(0, 0, 0, 10))[0].area() == 0);
+    }
+
+    #[test]
+    fn moves_to_the_rectangle_alongside() {
+        // 0 1 2
+        // 3 4 2
+        // 5 5 5
+        let rects = [
+            Rect::new(0, 0, 10, 5),
+            Rect::new(10, 0, 10, 5),
+            Rect::new(20, 0, 10, 10),
+            Rect::new(0, 5, 10, 5),
+            Rect::new(10, 5, 10, 5),
+            Rect::new(0, 10, 30, 5),
+            Rect::default(),
+        ];
+        assert_eq!(neighbour(&rects, 0, Direction::Right), Some(1));
+        assert_eq!(neighbour(&rects, 1, Direction::Right), Some(2));
+        assert_eq!(neighbour(&rects, 4, Direction::Right), Some(2));
+        assert_eq!(neighbour(&rects, 2, Direction::Left), Some(1));
+        assert_eq!(neighbour(&rects, 0, Direction::Down), Some(3));
+        assert_eq!(neighbour(&rects, 4, Direction::Up), Some(1));
+        assert_eq!(neighbour(&rects, 2, Direction::Down), Some(5));
+        assert_eq!(neighbour(&rects, 5, Direction::Up), Some(4));
+        // Nothing further that way
+        assert_eq!(neighbour(&rects, 0, Direction::Left), None);
+        assert_eq!(neighbour(&rects, 0, Direction::Up), None);
+        assert_eq!(neighbour(&rects, 5, Direction::Down), None);
+        // An item without a cell jumps to the first one that has one
+        assert_eq!(neighbour(&rects, 6, Direction::Right), Some(0));
+    }
+}